@@ -0,0 +1,221 @@
+use std::collections::BTreeMap;
+
+use chrono::Datelike;
+use serde::Serialize;
+
+use crate::{
+    feeding_report::FeedingReport,
+    headcount_report::HeadcountReport,
+    health_report::HealthReport,
+    herd::Herd,
+    late_arrival::arrived_by,
+    livestock::{DisposalPurpose, Livestock},
+    pasture::{Pasture, PastureKind},
+    snapshot::Snapshot,
+};
+
+// Summed up in f64, so the rounding errors of millions of f32 values don't add up
+#[derive(Default)]
+struct Mean {
+    sum: f64,
+    count: usize,
+}
+
+impl Mean {
+    fn add(&mut self, value: f64) {
+        self.sum += value;
+        self.count += 1;
+    }
+
+    fn value(&self) -> f64 {
+        self.sum / self.count as f64
+    }
+}
+
+#[derive(Serialize)]
+pub struct FillByPastureKindMonth {
+    pasture_kind: PastureKind,
+    year: i32,
+    month: u32,
+    report_count: usize,
+    avg_start_fill_pct: f64,
+    avg_end_fill_pct: f64,
+}
+
+fn fill_by_pasture_kind_month(
+    feeding_reports: &[&FeedingReport],
+    pastures: &[Pasture],
+) -> Vec<FillByPastureKindMonth> {
+    let mut groups: BTreeMap<(PastureKind, i32, u32), (Mean, Mean)> = BTreeMap::new();
+    for report in feeding_reports {
+        let kind = pastures[report.pasture_id].kind;
+        let (start_fill, end_fill) = groups
+            .entry((kind, report.date.year(), report.date.month()))
+            .or_default();
+        start_fill.add(report.start_fill_pct as f64);
+        end_fill.add(report.end_fill_pct as f64);
+    }
+    groups
+        .into_iter()
+        .map(
+            |((kind, year, month), (start_fill, end_fill))| FillByPastureKindMonth {
+                pasture_kind: kind,
+                year,
+                month,
+                report_count: start_fill.count,
+                avg_start_fill_pct: start_fill.value(),
+                avg_end_fill_pct: end_fill.value(),
+            },
+        )
+        .collect()
+}
+
+// The ratio of the sums of the counts, not the mean of the reports' ratios
+#[derive(Serialize)]
+pub struct HealthyRatioBySpeciesQuarter {
+    species_id: usize,
+    year: i32,
+    quarter: u32,
+    healthy_count: u64,
+    total_count: u64,
+    healthy_ratio: f64,
+}
+
+fn healthy_ratio_by_species_quarter(
+    health_reports: &[&HealthReport],
+    herds: &[Herd],
+) -> Vec<HealthyRatioBySpeciesQuarter> {
+    let mut groups: BTreeMap<(usize, i32, u32), (u64, u64)> = BTreeMap::new();
+    for report in health_reports {
+        let date = report.timestamp.date();
+        let (healthy_count, total_count) = groups
+            .entry((
+                herds[report.herd_id].species_id,
+                date.year(),
+                (date.month() - 1) / 3 + 1,
+            ))
+            .or_default();
+        *healthy_count += report.healthy_count as u64;
+        *total_count += report.total_count() as u64;
+    }
+    groups
+        .into_iter()
+        .map(
+            |((species_id, year, quarter), (healthy_count, total_count))| {
+                HealthyRatioBySpeciesQuarter {
+                    species_id,
+                    year,
+                    quarter,
+                    healthy_count,
+                    total_count,
+                    healthy_ratio: match total_count {
+                        0 => 1.,
+                        _ => healthy_count as f64 / total_count as f64,
+                    },
+                }
+            },
+        )
+        .collect()
+}
+
+#[derive(Serialize)]
+pub struct DisposalsByPurposeYear {
+    disposal_purpose: DisposalPurpose,
+    year: i32,
+    disposal_count: usize,
+    total_weight: f64, // in kilograms, of the animals that were weighed
+    total_price: f64,
+}
+
+fn disposals_by_purpose_year(livestock: &[Livestock]) -> Vec<DisposalsByPurposeYear> {
+    let mut groups: BTreeMap<(DisposalPurpose, i32), (usize, f64, f64)> = BTreeMap::new();
+    for animal in livestock {
+        let (disposal, purpose) = match (animal.disposal, animal.disposal_purpose) {
+            (Some(disposal), Some(purpose)) => (disposal, purpose),
+            _ => continue,
+        };
+        let (count, weight, price) = groups.entry((purpose, disposal.year())).or_default();
+        *count += 1;
+        *weight += animal.disposal_weight.unwrap_or(0.) as f64;
+        *price += animal.disposal_price.unwrap_or(0.) as f64;
+    }
+    groups
+        .into_iter()
+        .map(
+            |((purpose, year), (disposal_count, total_weight, total_price))| {
+                DisposalsByPurposeYear {
+                    disposal_purpose: purpose,
+                    year,
+                    disposal_count,
+                    total_weight,
+                    total_price,
+                }
+            },
+        )
+        .collect()
+}
+
+// Weeks are ISO weeks
+#[derive(Serialize)]
+pub struct HeadcountByHerdWeek {
+    herd_id: usize,
+    iso_year: i32,
+    iso_week: u32,
+    report_count: usize,
+    avg_quantity: f64,
+    max_quantity: u32,
+}
+
+fn headcount_by_herd_week(headcount_reports: &[&HeadcountReport]) -> Vec<HeadcountByHerdWeek> {
+    let mut groups: BTreeMap<(usize, i32, u32), (Mean, u32)> = BTreeMap::new();
+    for report in headcount_reports {
+        let week = report.timestamp.date().iso_week();
+        let (quantity, max_quantity) = groups
+            .entry((report.herd_id, week.year(), week.week()))
+            .or_default();
+        quantity.add(report.quantity as f64);
+        *max_quantity = (*max_quantity).max(report.quantity);
+    }
+    groups
+        .into_iter()
+        .map(
+            |((herd_id, iso_year, iso_week), (quantity, max_quantity))| HeadcountByHerdWeek {
+                herd_id,
+                iso_year,
+                iso_week,
+                report_count: quantity.count,
+                avg_quantity: quantity.value(),
+                max_quantity,
+            },
+        )
+        .collect()
+}
+
+// Reference results of aggregate queries over the tables of a snapshot, for checking what a
+// warehouse built on them answers; like the tables, they only cover the reports which arrived
+pub struct Aggregates {
+    pub fill_by_pasture_kind_month: Vec<FillByPastureKindMonth>,
+    pub healthy_ratio_by_species_quarter: Vec<HealthyRatioBySpeciesQuarter>,
+    pub disposals_by_purpose_year: Vec<DisposalsByPurposeYear>,
+    pub headcount_by_herd_week: Vec<HeadcountByHerdWeek>,
+}
+
+impl Aggregates {
+    pub fn new(snapshot: &Snapshot) -> Self {
+        Aggregates {
+            fill_by_pasture_kind_month: fill_by_pasture_kind_month(
+                &arrived_by(&snapshot.feeding_reports, snapshot.as_of),
+                &snapshot.pastures,
+            ),
+            healthy_ratio_by_species_quarter: healthy_ratio_by_species_quarter(
+                &arrived_by(&snapshot.health_reports, snapshot.as_of),
+                &snapshot.herds,
+            ),
+            disposals_by_purpose_year: disposals_by_purpose_year(&snapshot.livestock),
+            headcount_by_herd_week: headcount_by_herd_week(&arrived_by(
+                &snapshot.headcount_reports,
+                snapshot.as_of,
+            )),
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use rand::{prelude::SliceRandom, Rng};
+
+// Bank identifiers of some of the largest Polish banks
+const BANK_IDS: [u32; 10] = [
+    1020, // PKO Bank Polski
+    1050, // ING Bank Śląski
+    1090, // Santander Bank Polska
+    1140, // mBank
+    1160, // Bank Millennium
+    1240, // Bank Pekao
+    1320, // Bank Pocztowy
+    1600, // BNP Paribas Bank Polska
+    1870, // Nest Bank
+    2490, // Alior Bank
+];
+const SORT_CODE_WEIGHTS: [u32; 7] = [3, 9, 7, 1, 3, 9, 7];
+// "PL" with letters converted to numbers as in ISO 13616
+const POLAND_IBAN_CODE: &str = "2521";
+
+#[derive(Clone, Copy)]
+pub enum AccountNumberFormat {
+    Nrb,
+    Iban,
+}
+
+pub fn random_account_number<R: Rng>(rng: &mut R, format: AccountNumberFormat) -> String {
+    let bank_id = BANK_IDS.choose(rng).unwrap();
+    let branch_id = rng.gen_range(0..=999);
+    let sort_code = format!("{:04}{:03}", bank_id, branch_id);
+    let account = (0..16)
+        .map(|_| rng.gen_range(0..10).to_string())
+        .collect::<String>();
+    let bban = format!(
+        "{}{}{}",
+        sort_code,
+        sort_code_check_digit(&sort_code),
+        account
+    );
+    let nrb = format!("{:02}{}", iban_check_digits(&bban), bban);
+    match format {
+        AccountNumberFormat::Nrb => nrb,
+        AccountNumberFormat::Iban => format!("PL{}", nrb),
+    }
+}
+
+fn sort_code_check_digit(sort_code: &str) -> u32 {
+    let sum: u32 = sort_code
+        .chars()
+        .zip(SORT_CODE_WEIGHTS.iter())
+        .map(|(digit, weight)| digit.to_digit(10).unwrap() * weight)
+        .sum();
+    (10 - sum % 10) % 10
+}
+
+fn iban_check_digits(bban: &str) -> u32 {
+    // the number is far too large for any integer type, so it's reduced digit by digit
+    let remainder = format!("{}{}00", bban, POLAND_IBAN_CODE)
+        .chars()
+        .fold(0, |rem, digit| {
+            (rem * 10 + digit.to_digit(10).unwrap()) % 97
+        });
+    98 - remainder
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    // Whether the number passes the ISO 7064 mod 97 check, like banks validate it
+    fn is_valid_nrb(nrb: &str) -> bool {
+        let rearranged = format!("{}{}{}", &nrb[2..], POLAND_IBAN_CODE, &nrb[..2]);
+        let remainder = rearranged.chars().fold(0, |rem, digit| {
+            (rem * 10 + digit.to_digit(10).unwrap()) % 97
+        });
+        remainder == 1
+    }
+
+    #[test]
+    fn check_digits_of_known_account_numbers() {
+        for nrb in [
+            "61109010140000071219812874",
+            "27114020040000300201355387",
+            "10105000997603123456789123",
+        ] {
+            assert_eq!(
+                sort_code_check_digit(&nrb[2..9]),
+                nrb[9..10].parse().unwrap()
+            );
+            assert_eq!(iban_check_digits(&nrb[2..]), nrb[..2].parse().unwrap());
+            assert!(is_valid_nrb(nrb));
+        }
+    }
+
+    #[test]
+    fn random_account_numbers_are_valid() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..100 {
+            let nrb = random_account_number(&mut rng, AccountNumberFormat::Nrb);
+            assert_eq!(nrb.len(), 26);
+            assert!(is_valid_nrb(&nrb));
+            assert_eq!(
+                sort_code_check_digit(&nrb[2..9]),
+                nrb[9..10].parse().unwrap()
+            );
+
+            let iban = random_account_number(&mut rng, AccountNumberFormat::Iban);
+            assert_eq!(iban.len(), 28);
+            assert!(iban.starts_with("PL"));
+            assert!(is_valid_nrb(&iban[2..]));
+        }
+    }
+}
@@ -0,0 +1,221 @@
+use chrono::{Datelike, NaiveDate, NaiveTime, Timelike, Weekday};
+use serde::Serialize;
+
+use crate::{crop::is_sowing_season, shift::field_shift_of};
+
+const GRAZING_SEASON_START: (u32, u32) = (4, 15); // month and day
+const GRAZING_SEASON_END: (u32, u32) = (10, 31);
+const HARVEST_FIRST_MONTH: u32 = 7;
+const HARVEST_LAST_MONTH: u32 = 10;
+// Christmas Eve is a public holiday since 2025
+const CHRISTMAS_EVE_FIRST_YEAR: i32 = 2025;
+
+#[derive(Clone, Copy, Serialize)]
+pub enum Season {
+    Winter,
+    Spring,
+    Summer,
+    Autumn,
+}
+
+impl Season {
+    // Astronomical seasons, with the equinoxes and solstices on their usual days
+    fn of(day: NaiveDate) -> Self {
+        match (day.month(), day.day()) {
+            (1..=2, _) | (3, 1..=20) | (12, 22..=31) => Season::Winter,
+            (3, _) | (4..=5, _) | (6, 1..=21) => Season::Spring,
+            (6, _) | (7..=8, _) | (9, 1..=22) => Season::Summer,
+            _ => Season::Autumn,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Serialize)]
+pub enum DayPeriod {
+    Night,
+    Morning,
+    Afternoon,
+    Evening,
+}
+
+#[derive(Serialize)]
+pub struct DimDate {
+    date_key: u32,
+    pub date: NaiveDate,
+    year: i32,
+    quarter: u32,
+    month: u32,
+    month_name: String,
+    day_of_month: u32,
+    day_of_year: u32,
+    day_of_week: String,
+    iso_year: i32,
+    iso_week: u32,
+    is_weekend: bool,
+    is_holiday: bool,
+    holiday_name: Option<&'static str>,
+    is_working_day: bool,
+    season: Season,
+    is_grazing_season: bool,
+    is_sowing_season: bool,
+    is_harvest_season: bool,
+}
+
+impl DimDate {
+    pub fn new(date: NaiveDate) -> Self {
+        let is_weekend = matches!(date.weekday(), Weekday::Sat | Weekday::Sun);
+        let holiday_name = polish_holiday(date);
+        DimDate {
+            date_key: date_key(date),
+            date,
+            year: date.year(),
+            quarter: (date.month() - 1) / 3 + 1,
+            month: date.month(),
+            month_name: date.format("%B").to_string(),
+            day_of_month: date.day(),
+            day_of_year: date.ordinal(),
+            day_of_week: date.format("%A").to_string(),
+            iso_year: date.iso_week().year(),
+            iso_week: date.iso_week().week(),
+            is_weekend,
+            is_holiday: holiday_name.is_some(),
+            holiday_name,
+            is_working_day: !is_weekend && holiday_name.is_none(),
+            season: Season::of(date),
+            is_grazing_season: GRAZING_SEASON_START <= (date.month(), date.day())
+                && (date.month(), date.day()) <= GRAZING_SEASON_END,
+            is_sowing_season: is_sowing_season(date),
+            is_harvest_season: (HARVEST_FIRST_MONTH..=HARVEST_LAST_MONTH).contains(&date.month()),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct DimTime {
+    time: NaiveTime,
+    hour: u32,
+    minute: u32,
+    day_period: DayPeriod,
+    field_shift: u32, // which of the rotating shifts of the field staff is on duty
+}
+
+impl DimTime {
+    pub fn new(time: NaiveTime) -> Self {
+        DimTime {
+            time,
+            hour: time.hour(),
+            minute: time.minute(),
+            day_period: match time.hour() {
+                0..=5 => DayPeriod::Night,
+                6..=11 => DayPeriod::Morning,
+                12..=17 => DayPeriod::Afternoon,
+                _ => DayPeriod::Evening,
+            },
+            field_shift: field_shift_of(time.hour()),
+        }
+    }
+}
+
+// The smart key of the date dimension, e.g. 20240131
+pub fn date_key(date: NaiveDate) -> u32 {
+    date.year() as u32 * 10_000 + date.month() * 100 + date.day()
+}
+
+// Days which are already in the dimension aren't added again
+pub fn expand_dim_date_vec(
+    dim_dates: &mut Vec<DimDate>,
+    first_day: NaiveDate,
+    last_day: NaiveDate,
+) {
+    let mut day = match dim_dates.last() {
+        Some(last) => last.date.succ().max(first_day),
+        None => first_day,
+    };
+    while day <= last_day {
+        dim_dates.push(DimDate::new(day));
+        day = day.succ();
+    }
+}
+
+// Every minute of a day
+pub fn dim_time_vec() -> Vec<DimTime> {
+    (0..24 * 60)
+        .map(|minute| DimTime::new(NaiveTime::from_hms(minute / 60, minute % 60, 0)))
+        .collect()
+}
+
+fn polish_holiday(day: NaiveDate) -> Option<&'static str> {
+    let easter = easter_sunday(day.year());
+    let fixed = match (day.month(), day.day()) {
+        (1, 1) => Some("New Year's Day"),
+        (1, 6) => Some("Epiphany"),
+        (5, 1) => Some("Labour Day"),
+        (5, 3) => Some("Constitution Day"),
+        (8, 15) => Some("Assumption of Mary"),
+        (11, 1) => Some("All Saints' Day"),
+        (11, 11) => Some("Independence Day"),
+        (12, 24) if day.year() >= CHRISTMAS_EVE_FIRST_YEAR => Some("Christmas Eve"),
+        (12, 25) => Some("Christmas Day"),
+        (12, 26) => Some("Second Day of Christmas"),
+        _ => None,
+    };
+    fixed.or_else(|| match day.signed_duration_since(easter).num_days() {
+        0 => Some("Easter Sunday"),
+        1 => Some("Easter Monday"),
+        49 => Some("Pentecost"),
+        60 => Some("Corpus Christi"),
+        _ => None,
+    })
+}
+
+// The anonymous Gregorian algorithm
+fn easter_sunday(year: i32) -> NaiveDate {
+    let a = year % 19;
+    let b = year / 100;
+    let c = year % 100;
+    let d = b / 4;
+    let e = b % 4;
+    let f = (b + 8) / 25;
+    let g = (b - f + 1) / 3;
+    let h = (19 * a + b - d - g + 15) % 30;
+    let i = c / 4;
+    let k = c % 4;
+    let l = (32 + 2 * e + 2 * i - h - k) % 7;
+    let m = (a + 11 * h + 22 * l) / 451;
+    let month = (h + l - 7 * m + 114) / 31;
+    let day = (h + l - 7 * m + 114) % 31 + 1;
+    NaiveDate::from_ymd(year, month as u32, day as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn easter_sunday_of_known_years() {
+        for (year, month, day) in [
+            (1818, 3, 22),
+            (2000, 4, 23),
+            (2019, 4, 21),
+            (2024, 3, 31),
+            (2025, 4, 20),
+            (2026, 4, 5),
+            (2038, 4, 25),
+        ] {
+            assert_eq!(easter_sunday(year), NaiveDate::from_ymd(year, month, day));
+        }
+    }
+
+    #[test]
+    fn polish_holidays() {
+        let holiday = |year, month, day| polish_holiday(NaiveDate::from_ymd(year, month, day));
+        assert_eq!(holiday(2026, 1, 6), Some("Epiphany"));
+        assert_eq!(holiday(2026, 4, 6), Some("Easter Monday"));
+        assert_eq!(holiday(2024, 5, 19), Some("Pentecost"));
+        assert_eq!(holiday(2025, 6, 19), Some("Corpus Christi"));
+        assert_eq!(holiday(2025, 11, 11), Some("Independence Day"));
+        assert_eq!(holiday(2025, 12, 24), Some("Christmas Eve"));
+        assert_eq!(holiday(2024, 12, 24), None);
+        assert_eq!(holiday(2025, 6, 20), None);
+    }
+}
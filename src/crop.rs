@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+
+use chrono::{Datelike, Duration, NaiveDate};
+use rand::{distributions::Slice, prelude::Distribution, Rng};
+use serde::{Deserialize, Serialize};
+
+use crate::{field::Field, parallel::generator_rng, snapshot::Expansion, species::Species};
+
+const SOWING_FIRST_MONTH: u32 = 4;
+const SOWING_LAST_MONTH: u32 = 10;
+const MIN_FALLOW_DAYS: i64 = 7;
+const MAX_FALLOW_DAYS: i64 = 60;
+
+#[derive(Serialize, Deserialize)]
+pub struct Crop {
+    pub id: usize,
+    pub field_id: usize,
+    pub species_id: usize,
+    pub sowing_date: NaiveDate,
+    pub harvest_date: Option<NaiveDate>,
+
+    #[serde(skip, default = "unplanned")]
+    pub planned_harvest_date: NaiveDate,
+}
+
+impl Crop {
+    pub fn new(id: usize, field: &Field, species: &Species, sowing_date: NaiveDate) -> Self {
+        Crop {
+            id,
+            field_id: field.id,
+            species_id: species.id,
+            sowing_date,
+            harvest_date: None,
+            planned_harvest_date: planned_harvest_date(species, sowing_date),
+        }
+    }
+}
+
+const fn unplanned() -> NaiveDate {
+    chrono::naive::MIN_DATE
+}
+
+fn planned_harvest_date(species: &Species, sowing_date: NaiveDate) -> NaiveDate {
+    sowing_date + Duration::from_std(species.lifespan).unwrap()
+}
+
+// The planned harvests aren't saved, but they only depend on the species and the sowing
+pub fn restore_planned_harvest_dates(crops: &mut [Crop], species: &[Species]) {
+    for crop in crops.iter_mut() {
+        crop.planned_harvest_date =
+            planned_harvest_date(&species[crop.species_id], crop.sowing_date);
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Harvest {
+    pub id: usize,
+    pub crop_id: usize,
+    pub field_id: usize,
+    pub species_id: usize,
+    pub date: NaiveDate,
+    pub quantity: f32, // in kilograms
+    yield_per_area: f32,
+}
+
+impl Harvest {
+    pub const fn new(
+        id: usize,
+        crop: &Crop,
+        date: NaiveDate,
+        quantity: f32,
+        yield_per_area: f32,
+    ) -> Self {
+        Harvest {
+            id,
+            crop_id: crop.id,
+            field_id: crop.field_id,
+            species_id: crop.species_id,
+            date,
+            quantity,
+            yield_per_area,
+        }
+    }
+}
+
+// Fields are sown one crop after another, with some fallow time in between; crops that won't ripen
+// before the last day are left growing until the next expansion, and so are the sowings planned
+// after it
+pub fn expand_crop_vec(
+    crops: &mut Vec<Crop>,
+    harvests: &mut Vec<Harvest>,
+    planned_sowings: &mut Vec<Option<NaiveDate>>, // per field, while no crop is growing on it
+    fields: &[Field],
+    species: &[Species],
+    expansion: &Expansion,
+) {
+    let mut rng = generator_rng();
+    let (first_day, last_day) = (expansion.first_day(), expansion.last_day());
+    let species_idxs_distribution = Slice::new(&expansion.config.species_for_fields).unwrap();
+
+    let mut last_crops = HashMap::new();
+    for crop in crops.iter() {
+        last_crops.insert(crop.field_id, crop.id);
+    }
+
+    planned_sowings.resize(fields.len(), None);
+    for field in fields {
+        let last_crop = last_crops
+            .get(&field.id)
+            .map(|&crop_id| &mut crops[crop_id]);
+        let mut next_sowing = match (last_crop, planned_sowings[field.id]) {
+            (Some(crop), _) if crop.harvest_date.is_none() => {
+                if crop.planned_harvest_date >= last_day {
+                    continue;
+                }
+                harvest_crop(&mut rng, harvests, crop, field, &species[crop.species_id]);
+                crop.harvest_date.unwrap() + random_fallow(&mut rng)
+            }
+            (_, Some(planned_sowing)) => planned_sowing,
+            (None, None) => first_day + Duration::days(rng.gen_range(0..MAX_FALLOW_DAYS)),
+            // the plan isn't saved with a snapshot, and the fallow after a harvest of an earlier
+            // expansion was already part of it, so the field can't be sown before this one starts
+            (Some(crop), None) => {
+                (crop.harvest_date.unwrap() + random_fallow(&mut rng)).max(first_day)
+            }
+        };
+        planned_sowings[field.id] = None;
+
+        loop {
+            next_sowing = in_sowing_season(next_sowing);
+            if next_sowing >= last_day {
+                planned_sowings[field.id] = Some(next_sowing);
+                break;
+            }
+            let species = &species[*species_idxs_distribution.sample(&mut rng)];
+            let mut crop = Crop::new(crops.len(), field, species, next_sowing);
+            if crop.planned_harvest_date >= last_day {
+                crops.push(crop);
+                break;
+            }
+            harvest_crop(&mut rng, harvests, &mut crop, field, species);
+            next_sowing = crop.harvest_date.unwrap() + random_fallow(&mut rng);
+            crops.push(crop);
+        }
+    }
+}
+
+fn harvest_crop<R: Rng>(
+    rng: &mut R,
+    harvests: &mut Vec<Harvest>,
+    crop: &mut Crop,
+    field: &Field,
+    species: &Species,
+) {
+    let crop_yield = species.crop_yield.as_ref().unwrap();
+    let yield_per_area = rng.gen_range(crop_yield.min_per_area..=crop_yield.max_per_area);
+    crop.harvest_date = Some(crop.planned_harvest_date);
+    harvests.push(Harvest::new(
+        harvests.len(),
+        crop,
+        crop.planned_harvest_date,
+        field.area * yield_per_area,
+        yield_per_area,
+    ));
+}
+
+fn random_fallow<R: Rng>(rng: &mut R) -> Duration {
+    Duration::days(rng.gen_range(MIN_FALLOW_DAYS..=MAX_FALLOW_DAYS))
+}
+
+pub fn is_sowing_season(day: NaiveDate) -> bool {
+    (SOWING_FIRST_MONTH..=SOWING_LAST_MONTH).contains(&day.month())
+}
+
+fn in_sowing_season(day: NaiveDate) -> NaiveDate {
+    if day.month() < SOWING_FIRST_MONTH {
+        NaiveDate::from_ymd(day.year(), SOWING_FIRST_MONTH, 1)
+    } else if day.month() > SOWING_LAST_MONTH {
+        NaiveDate::from_ymd(day.year() + 1, SOWING_FIRST_MONTH, 1)
+    } else {
+        day
+    }
+}
@@ -0,0 +1,63 @@
+use rand::{prelude::SliceRandom, Rng};
+use serde::{Deserialize, Serialize};
+
+use crate::{locale::NamePack, parallel::generator_rng};
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum CustomerKind {
+    Wholesaler,
+    Retailer,
+    Butcher,
+    Dairy,
+    Restaurant,
+}
+
+impl CustomerKind {
+    const ALL: [CustomerKind; 5] = [
+        CustomerKind::Wholesaler,
+        CustomerKind::Retailer,
+        CustomerKind::Butcher,
+        CustomerKind::Dairy,
+        CustomerKind::Restaurant,
+    ];
+
+    const fn name_suffix(&self) -> &'static str {
+        match self {
+            CustomerKind::Wholesaler => "Wholesale",
+            CustomerKind::Retailer => "Foods",
+            CustomerKind::Butcher => "Meats",
+            CustomerKind::Dairy => "Dairy",
+            CustomerKind::Restaurant => "Bistro",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Customer {
+    pub id: usize,
+    name: String,
+    pub kind: CustomerKind,
+}
+
+impl Customer {
+    pub const fn new(id: usize, name: String, kind: CustomerKind) -> Self {
+        Customer { id, name, kind }
+    }
+}
+
+// Customers are companies named after their owners
+pub fn expand_customer_vec(customers: &mut Vec<Customer>, count: usize, name_packs: &[NamePack]) {
+    let mut rng = generator_rng();
+
+    for _ in 0..count {
+        let kind = *CustomerKind::ALL.choose(&mut rng).unwrap();
+        let name_pack = name_packs.choose(&mut rng).unwrap();
+        let owner = name_pack.surnames.choose(&mut rng).unwrap();
+        let name = if rng.gen_bool(0.5) {
+            format!("{} {}", owner, kind.name_suffix())
+        } else {
+            format!("{} & Sons {}", owner, kind.name_suffix())
+        };
+        customers.push(Customer::new(customers.len(), name, kind));
+    }
+}
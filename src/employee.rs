@@ -1,71 +1,222 @@
+use std::{collections::HashSet, iter};
+
+use chrono::{Duration, NaiveDate};
+use enum_map::{Enum, EnumMap};
 use rand::{
     distributions::{Slice, Uniform},
-    prelude::Distribution,
+    prelude::{Distribution, SliceRandom},
     Rng,
 };
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bank_account::{random_account_number, AccountNumberFormat},
+    locale::NamePack,
+    parallel::generator_rng,
+    pesel::{corrupt_pesel, random_pesel},
+};
+
+const MIN_AGE_YEARS: i64 = 18;
+const MAX_AGE_YEARS: i64 = 65;
+const MAX_UNIQUE_IDENTITY_ATTEMPTS: usize = 1000;
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub enum Sex {
+    Male,
+    Female,
+}
+
+#[derive(Clone, Copy, Enum, PartialEq, Serialize, Deserialize)]
+pub enum EmployeeRole {
+    Veterinarian,
+    Herdsman,
+    WarehouseManager,
+    Administrator,
+}
+
+pub struct SalaryMinMax {
+    pub min: f32,
+    pub max: f32,
+}
+
+pub struct HiringConfig {
+    pub name_packs: Vec<NamePack>,
+    pub unique_identities: bool, // no two employees share a full name or a PESEL
+    pub salary_ranges: EnumMap<EmployeeRole, SalaryMinMax>,
+    pub invalid_pesel_pct: f32, // typos made when entering the PESEL
+    pub account_number_format: AccountNumberFormat,
+}
+
+// Who an employee is, as opposed to the terms they're employed on
+pub struct Identity {
+    pub name: String,
+    pub surname: String,
+    pub sex: Sex,
+    pub birth_date: NaiveDate,
+    pub pesel: String,
+}
 
-#[derive(Serialize)]
-pub struct Employee<'a, 'b> {
+#[derive(Serialize, Deserialize)]
+pub struct Employee {
     pub id: usize,
-    name: &'a str,
-    surname: &'b str,
-    pesel: u64,
+    pub name: String,
+    pub surname: String,
+    pesel: String,
     account_number: String,
-    salary: f32,
+    pub salary: f32,
+    pub sex: Sex,
+    birth_date: NaiveDate,
+    pub role: EmployeeRole,
+    pub hire_date: NaiveDate,
+    pub termination_date: Option<NaiveDate>,
 }
 
-impl<'a, 'b> Employee<'a, 'b> {
-    pub const fn new(
+impl Employee {
+    pub fn new(
         id: usize,
-        name: &'a str,
-        surname: &'b str,
-        pesel: u64,
+        identity: Identity,
         account_number: String,
         salary: f32,
+        role: EmployeeRole,
+        hire_date: NaiveDate,
     ) -> Self {
         Self {
             id,
-            name,
-            surname,
-            pesel,
+            name: identity.name,
+            surname: identity.surname,
+            pesel: identity.pesel,
             account_number,
             salary,
+            sex: identity.sex,
+            birth_date: identity.birth_date,
+            role,
+            hire_date,
+            termination_date: None,
         }
     }
+
+    pub fn is_employed_on(&self, day: NaiveDate) -> bool {
+        self.hire_date <= day && self.termination_date.is_none_or(|last_day| day <= last_day)
+    }
 }
 
 pub fn expand_employee_vec(
     employees: &mut Vec<Employee>,
-    count: usize,
-    names: &[&'static str],
-    surnames: &[&'static str],
-    salary_min: f32,
-    salary_max: f32,
+    counts: &EnumMap<EmployeeRole, usize>,
+    config: &HiringConfig,
+    hire_date: NaiveDate,
 ) {
-    let mut rng = rand::thread_rng();
-    let name_distribution = Slice::new(names).unwrap();
-    let surname_distribution = Slice::new(surnames).unwrap();
-    let salary_distribution = Uniform::new(salary_min, salary_max);
+    let vacancies: Vec<(EmployeeRole, NaiveDate)> = counts
+        .iter()
+        .flat_map(|(role, &count)| iter::repeat_n((role, hire_date), count))
+        .collect();
+    hire_employees(employees, &vacancies, config);
+}
+
+// Fills every vacancy, a role to be taken up on the given day, with someone new
+pub fn hire_employees(
+    employees: &mut Vec<Employee>,
+    vacancies: &[(EmployeeRole, NaiveDate)],
+    config: &HiringConfig,
+) {
+    let mut rng = generator_rng();
+    let name_pack_distribution = Slice::new(&config.name_packs).unwrap();
+    let age_days_distribution = Uniform::new_inclusive(MIN_AGE_YEARS * 365, MAX_AGE_YEARS * 365);
+
+    let mut taken_full_names: HashSet<(String, String)> = employees
+        .iter()
+        .map(|employee| (employee.name.clone(), employee.surname.clone()))
+        .collect();
+    let mut taken_pesels: HashSet<String> = employees
+        .iter()
+        .map(|employee| employee.pesel.clone())
+        .collect();
 
-    for _ in 0..count {
+    for &(role, hire_date) in vacancies {
+        let salary_range = &config.salary_ranges[role];
+        let salary_distribution = Uniform::new(salary_range.min, salary_range.max);
+        let mut attempts = 0;
+        let identity = loop {
+            let name_pack = name_pack_distribution.sample(&mut rng);
+            let sex = if rng.gen_bool(0.5) {
+                Sex::Male
+            } else {
+                Sex::Female
+            };
+            let name = name_pack.names(sex).choose(&mut rng).unwrap().clone();
+            let surname = name_pack.surname_for(name_pack.surnames.choose(&mut rng).unwrap(), sex);
+            let birth_date = hire_date - Duration::days(age_days_distribution.sample(&mut rng));
+            let mut pesel = random_pesel(&mut rng, birth_date, sex);
+            if rng.gen_bool(config.invalid_pesel_pct as f64) {
+                pesel = corrupt_pesel(&mut rng, &pesel);
+            }
+
+            let full_name = (name, surname);
+            if !config.unique_identities
+                || (!taken_full_names.contains(&full_name) && !taken_pesels.contains(&pesel))
+            {
+                break Identity {
+                    name: full_name.0,
+                    surname: full_name.1,
+                    sex,
+                    birth_date,
+                    pesel,
+                };
+            }
+            attempts += 1;
+            if attempts > MAX_UNIQUE_IDENTITY_ATTEMPTS {
+                panic!("The name packs are too small to generate unique employee identities");
+            }
+        };
+        if config.unique_identities {
+            taken_full_names.insert((identity.name.clone(), identity.surname.clone()));
+            taken_pesels.insert(identity.pesel.clone());
+        }
         employees.push(Employee::new(
             employees.len(),
-            name_distribution.sample(&mut rng),
-            surname_distribution.sample(&mut rng),
-            random_pesel(),
-            random_account_number(),
+            identity,
+            random_account_number(&mut rng, config.account_number_format),
             salary_distribution.sample(&mut rng),
+            role,
+            hire_date,
         ))
     }
 }
 
-fn random_pesel() -> u64 {
-    rand::thread_rng().gen_range(0u64..=99_99_99_99999)
+pub fn employees_with_role(employees: &[Employee], role: EmployeeRole) -> Vec<&Employee> {
+    employees
+        .iter()
+        .filter(|employee| employee.role == role)
+        .collect()
 }
 
-fn random_account_number() -> String {
-    rand::thread_rng()
-        .gen_range(0u128..=9999_9999_9999_9999_9999_9999)
-        .to_string()
+// Every employee is dismissed with the given chance, so short expansions with a proportionally
+// smaller chance still dismiss someone now and then; warehouse managers are never dismissed, as
+// their warehouses would be left without one. Returns the vacancies left, to be filled the day after
+// each dismissal so that no role ever empties out
+pub fn dismiss_employees(
+    employees: &mut [Employee],
+    dismiss_pct: f32,
+    first_day: NaiveDate,
+    last_day: NaiveDate,
+) -> Vec<(EmployeeRole, NaiveDate)> {
+    let mut rng = generator_rng();
+    let mut vacancies = vec![];
+
+    for employee in employees.iter_mut().filter(|employee| {
+        employee.termination_date.is_none() && employee.role != EmployeeRole::WarehouseManager
+    }) {
+        if !rng.gen_bool(dismiss_pct as f64) {
+            continue;
+        }
+        let first_day = first_day.max(employee.hire_date);
+        if first_day >= last_day {
+            continue;
+        }
+        let employed_days = last_day.signed_duration_since(first_day).num_days();
+        let termination_date = first_day + Duration::days(rng.gen_range(0..employed_days));
+        employee.termination_date = Some(termination_date);
+        vacancies.push((employee.role, termination_date.succ()));
+    }
+    vacancies
 }
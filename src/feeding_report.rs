@@ -1,71 +1,118 @@
 use chrono::{prelude::*, Duration};
 use rand::Rng;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-use crate::pasture::Pasture;
+use crate::{
+    late_arrival::{unknown_arrival, LateArriving},
+    parallel::{entity_rng, generator_rng, par_map},
+    pasture::Pasture,
+    table::Table,
+    weather::{weather_on, Weather},
+};
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize)]
 pub struct FeedingReport {
     pub id: usize,
     pub date: NaiveDate,
     pub pasture_id: usize,
     pub start_fill_pct: f32,
     pub end_fill_pct: f32,
+
+    #[serde(skip, default = "unknown_arrival")]
+    recorded_at: NaiveDateTime,
 }
 
 impl FeedingReport {
-    pub const fn new(
+    pub fn new(
         id: usize,
-        date: NaiveDate,
+        date: NaiveDateTime,
         pasture: &Pasture,
         start_fill_pct: f32,
         end_fill_pct: f32,
     ) -> Self {
         FeedingReport {
             id,
-            date,
+            date: date.date(),
             pasture_id: pasture.id,
             start_fill_pct,
             end_fill_pct,
+            recorded_at: date.date().and_hms(0, 0, 0),
         }
     }
 }
 
+impl LateArriving for FeedingReport {
+    fn occurred_at(&self) -> NaiveDateTime {
+        self.date.and_hms(0, 0, 0)
+    }
+
+    fn recorded_at(&self) -> NaiveDateTime {
+        self.recorded_at
+    }
+
+    fn set_recorded_at(&mut self, recorded_at: NaiveDateTime) {
+        self.recorded_at = recorded_at;
+    }
+
+    fn correct<R: Rng>(&mut self, rng: &mut R) {
+        // lowering the start fill keeps it below both the end fill and the previous report's end
+        self.start_fill_pct = rng.gen_range(0.0..=self.start_fill_pct);
+    }
+}
+
 pub fn expand_feeding_report_vec(
-    feeding_reports: &mut Vec<FeedingReport>,
-    pastures: &Vec<Pasture>,
+    feeding_reports: &mut Table<FeedingReport>,
+    feeder_fills: &[f32], // of every pasture's feeder after its latest report, empty for new ones
+    pastures: &[Pasture],
+    weather: &[Weather],
     first_report_dt: NaiveDateTime,
     last_report_dt: NaiveDateTime,
     report_interval: Duration,
 ) {
-    let count_per_pasture = last_report_dt
+    let count_per_pasture = (last_report_dt
         .signed_duration_since(first_report_dt)
         .num_seconds()
-        / report_interval.num_seconds();
+        / report_interval.num_seconds())
+    .max(1) as usize;
+    let first_id = feeding_reports.next_id();
+    let seed = generator_rng().gen();
 
-    let mut rng = rand::thread_rng();
-
-    for pasture in pastures {
+    // pastures are independent of each other, so they're generated in parallel
+    let pasture_reports = par_map(pastures, |pasture| {
+        let mut rng = entity_rng(seed, pasture.id);
+        let first_id = first_id + pasture.id * count_per_pasture;
+        let mut reports: Vec<FeedingReport> = Vec::with_capacity(count_per_pasture);
         let mut date = first_report_dt;
-        feeding_reports.push(FeedingReport::new(
-            feeding_reports.len(),
-            date.date(),
-            pasture,
-            0.0,
-            rng.gen_range(0.0..100.0),
-        ));
-        for _ in 0..(count_per_pasture - 1) {
-            date += report_interval;
-            let prev_report = feeding_reports.last().unwrap();
-            let start_fill = rng.gen_range(0.0..prev_report.end_fill_pct);
-            let end_fill = rng.gen_range(start_fill..=100.0);
-            feeding_reports.push(FeedingReport::new(
-                feeding_reports.len(),
-                date.date(),
+        let mut end_fill = feeder_fills.get(pasture.id).copied().unwrap_or(0.);
+        for _ in 0..count_per_pasture {
+            // the colder it is, the more of the feeder is eaten up
+            let demand = weather_on(weather, date.date()).map_or(1., Weather::feed_demand_factor);
+            let eaten_pct = (rng.gen_range(0.0..1.0) * demand).min(1.);
+            let start_fill = end_fill * (1. - eaten_pct);
+            end_fill = rng.gen_range(start_fill..=100.0);
+            reports.push(FeedingReport::new(
+                first_id + reports.len(),
+                date,
                 pasture,
                 start_fill,
                 end_fill,
             ));
+            date += report_interval;
         }
+        reports
+    });
+    feeding_reports.extend(pasture_reports.into_iter().flatten());
+}
+
+// Sets every pasture's feeder to how full it was left by the latest of the reports, which come in
+// date order for a pasture
+pub fn update_feeder_fills(
+    feeder_fills: &mut Vec<f32>,
+    feeding_reports: &[FeedingReport],
+    pastures: &[Pasture],
+) {
+    feeder_fills.resize(pastures.len(), 0.);
+    for report in feeding_reports {
+        feeder_fills[report.pasture_id] = report.end_fill_pct;
     }
 }
@@ -0,0 +1,24 @@
+use rand::{self, Rng};
+use serde::{Deserialize, Serialize};
+
+use crate::parallel::generator_rng;
+
+#[derive(Serialize, Deserialize)]
+pub struct Field {
+    pub id: usize,
+    pub area: f32,
+}
+
+impl Field {
+    pub const fn new(id: usize, area: f32) -> Self {
+        Field { id, area }
+    }
+}
+
+pub fn expand_field_vec(fields: &mut Vec<Field>, count: usize, area_min: f32, area_max: f32) {
+    let mut rng = generator_rng();
+
+    for _ in 0..count {
+        fields.push(Field::new(fields.len(), rng.gen_range(area_min..=area_max)));
+    }
+}
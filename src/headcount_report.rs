@@ -1,19 +1,31 @@
-use chrono::{Duration, NaiveDateTime};
-use rand::{
-    distributions::{Slice, Uniform},
-    prelude::Distribution,
-};
-use serde::Serialize;
+use chrono::NaiveDateTime;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
 
-use crate::{employee::Employee, herd::Herd};
+use crate::{
+    employee::Employee,
+    herd::Herd,
+    late_arrival::{unknown_arrival, LateArriving},
+    livestock_movement::HerdMembership,
+    parallel::{entity_rng, generator_rng, par_map},
+    shift::Roster,
+    snapshot::Expansion,
+    table::Table,
+};
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize)]
 pub struct HeadcountReport {
-    id: usize,
-    employee_id: usize,
+    pub id: usize,
+    pub employee_id: usize,
     pub herd_id: usize,
     pub timestamp: NaiveDateTime,
     pub quantity: u32,
+    pub overstocked: bool, // more animals than the herd's area fits
+
+    #[serde(skip)]
+    herd_capacity: usize,
+    #[serde(skip, default = "unknown_arrival")]
+    recorded_at: NaiveDateTime,
 }
 
 impl HeadcountReport {
@@ -30,40 +42,82 @@ impl HeadcountReport {
             herd_id: herd.id,
             timestamp,
             quantity,
+            overstocked: quantity as usize > herd.capacity,
+            herd_capacity: herd.capacity,
+            recorded_at: timestamp,
         }
     }
 }
 
+impl LateArriving for HeadcountReport {
+    fn occurred_at(&self) -> NaiveDateTime {
+        self.timestamp
+    }
+
+    fn recorded_at(&self) -> NaiveDateTime {
+        self.recorded_at
+    }
+
+    fn set_recorded_at(&mut self, recorded_at: NaiveDateTime) {
+        self.recorded_at = recorded_at;
+    }
+
+    fn correct<R: Rng>(&mut self, rng: &mut R) {
+        // a recount, off by a few percent at most
+        let max_delta = (self.quantity / 50).max(1) as i64;
+        let quantity = self.quantity as i64 + rng.gen_range(-max_delta..=max_delta);
+        self.quantity = quantity.max(0) as u32;
+        self.overstocked = self.quantity as usize > self.herd_capacity;
+    }
+}
+
+// The herds' capacities aren't saved with the reports, so they're looked up again after loading
+pub fn restore_herd_capacities(headcount_reports: &mut [HeadcountReport], herds: &[Herd]) {
+    for report in headcount_reports.iter_mut() {
+        report.herd_capacity = herds[report.herd_id].capacity;
+    }
+}
+
 pub fn expand_headcount_report_vec(
-    headcount_reports: &mut Vec<HeadcountReport>,
-    herds: &Vec<Herd>,
-    employees: &Vec<Employee>,
-    min_count: u32,
-    max_count: u32,
-    first_report_dt: NaiveDateTime,
-    last_report_dt: NaiveDateTime,
-    report_interval: Duration,
+    headcount_reports: &mut Table<HeadcountReport>,
+    herds: &[Herd],
+    membership: &HerdMembership,
+    herdsmen: &Roster,
+    expansion: &Expansion,
 ) {
-    let count_per_herd = last_report_dt
-        .signed_duration_since(first_report_dt)
+    let report_interval = expansion.config.report_interval;
+    let count_per_herd = (expansion
+        .to_when
+        .signed_duration_since(expansion.from_when)
         .num_seconds()
-        / report_interval.num_seconds();
-
-    let mut rng = rand::thread_rng();
-    let employee_distribution = Slice::new(employees).unwrap();
-    let quantity_distribution = Uniform::new(min_count, max_count);
+        / report_interval.num_seconds()) as usize;
+    let seed = generator_rng().gen();
 
-    for herd in herds {
-        let mut timestamp = first_report_dt;
+    // herds are counted independently of each other, so they're generated in parallel
+    let herd_reports = par_map(herds, |herd| {
+        let mut rng = entity_rng(seed, herd.id);
+        let mut reports = Vec::with_capacity(count_per_herd);
+        let mut timestamp = expansion.from_when;
         for _ in 0..count_per_herd {
-            headcount_reports.push(HeadcountReport::new(
-                headcount_reports.len(),
-                employee_distribution.sample(&mut rng),
-                herd,
-                timestamp,
-                quantity_distribution.sample(&mut rng),
-            ));
+            // the herd goes uncounted while there's no herdsman employed
+            if let Some((herdsman, on_duty_timestamp)) =
+                herdsmen.assign(&mut rng, timestamp, timestamp + report_interval)
+            {
+                reports.push(HeadcountReport::new(
+                    0,
+                    herdsman,
+                    herd,
+                    on_duty_timestamp,
+                    membership.count_at(herd.id, on_duty_timestamp) as u32,
+                ));
+            }
             timestamp += report_interval;
         }
+        reports
+    });
+    // the reports are numbered once they're all in, as some of them may have been skipped
+    for mut report in herd_reports.into_iter().flatten() {
+        report.id = headcount_reports.next_id();
+        headcount_reports.push(report);
     }
 }
@@ -1,22 +1,52 @@
+use std::collections::HashMap;
+
 use chrono::NaiveDateTime;
-use rand::{
-    distributions::{Slice, Uniform},
-    prelude::Distribution,
+use rand::{distributions::Uniform, prelude::Distribution, Rng};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    employee::Employee,
+    headcount_report::HeadcountReport,
+    herd::Herd,
+    late_arrival::{correct_records, unknown_arrival, LateArrivalConfig, LateArriving},
+    parallel::generator_rng,
+    shift::Roster,
+    snapshot::Expansion,
+    table::Table,
+    weather::{weather_on, Weather},
 };
-use serde::Serialize;
 
-use crate::{employee::Employee, headcount_report::HeadcountReport, herd::Herd};
+// Upper bounds of the share of a herd found in each state of illness by an examination, before the
+// weather makes them worse
+pub struct IllnessConfig {
+    pub ill_max_pct: f32,
+    pub severly_ill_max_pct: f32,
+    pub terminal_max_pct: f32,
+}
+
+// How many of the examined animals are in each state of health
+pub struct HealthCounts {
+    pub healthy: u32,
+    pub ill: u32,
+    pub severly_ill: u32,
+    pub terminal: u32,
+}
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize)]
 pub struct HealthReport {
     pub id: usize,
-    employee_id: usize,
-    herd_id: usize,
-    timestamp: NaiveDateTime,
-    healthy_count: u32,
-    ill_count: u32,
-    severly_ill_count: u32,
-    terminal_count: u32,
+    pub employee_id: usize,
+    pub herd_id: usize,
+    pub timestamp: NaiveDateTime,
+    pub healthy_count: u32,
+    pub ill_count: u32,
+    pub severly_ill_count: u32,
+    pub terminal_count: u32,
+
+    #[serde(skip)]
+    headcount_report_id: Option<usize>, // not known for the reports loaded from a snapshot
+    #[serde(skip, default = "unknown_arrival")]
+    recorded_at: NaiveDateTime,
 }
 
 impl HealthReport {
@@ -24,57 +54,173 @@ impl HealthReport {
         id: usize,
         doctor: &Employee,
         herd: &Herd,
+        headcount: &HeadcountReport,
         timestamp: NaiveDateTime,
-        healthy_count: u32,
-        ill_count: u32,
-        severly_ill_count: u32,
-        terminal_count: u32,
+        counts: HealthCounts,
     ) -> Self {
         Self {
             id,
             employee_id: doctor.id,
             herd_id: herd.id,
             timestamp,
-            ill_count,
-            severly_ill_count,
-            terminal_count,
-            healthy_count,
+            ill_count: counts.ill,
+            severly_ill_count: counts.severly_ill,
+            terminal_count: counts.terminal,
+            healthy_count: counts.healthy,
+            headcount_report_id: Some(headcount.id),
+            recorded_at: timestamp,
+        }
+    }
+
+    pub const fn total_count(&self) -> u32 {
+        self.healthy_count + self.ill_count + self.severly_ill_count + self.terminal_count
+    }
+
+    pub fn healthy_ratio(&self) -> f32 {
+        match self.total_count() {
+            0 => 1.,
+            total_count => self.healthy_count as f32 / total_count as f32,
+        }
+    }
+
+    // Brings the total in line with a recount of the herd, the animals added or missing being the
+    // healthy ones first
+    fn recount(&mut self, total_count: u32) {
+        if total_count >= self.total_count() {
+            self.healthy_count += total_count - self.total_count();
+            return;
+        }
+        let mut missing = self.total_count() - total_count;
+        for count in [
+            &mut self.healthy_count,
+            &mut self.ill_count,
+            &mut self.severly_ill_count,
+            &mut self.terminal_count,
+        ] {
+            let removed = missing.min(*count);
+            *count -= removed;
+            missing -= removed;
         }
     }
 }
 
+impl LateArriving for HealthReport {
+    fn occurred_at(&self) -> NaiveDateTime {
+        self.timestamp
+    }
+
+    fn recorded_at(&self) -> NaiveDateTime {
+        self.recorded_at
+    }
+
+    fn set_recorded_at(&mut self, recorded_at: NaiveDateTime) {
+        self.recorded_at = recorded_at;
+    }
+
+    fn correct<R: Rng>(&mut self, rng: &mut R) {
+        // a re-diagnosis moves some of the healthy animals to ill, the total stays the same
+        let moved = rng.gen_range(0..=self.healthy_count / 20);
+        self.healthy_count -= moved;
+        self.ill_count += moved;
+    }
+}
+
+// A headcount together with the health report of the examination which followed it, so that a
+// recount changes the total the vet examined as well
+struct Recount<'a> {
+    headcount: &'a mut HeadcountReport,
+    health: &'a mut HealthReport,
+}
+
+impl LateArriving for Recount<'_> {
+    fn occurred_at(&self) -> NaiveDateTime {
+        self.headcount.occurred_at()
+    }
+
+    fn recorded_at(&self) -> NaiveDateTime {
+        self.headcount.recorded_at().max(self.health.recorded_at())
+    }
+
+    fn set_recorded_at(&mut self, recorded_at: NaiveDateTime) {
+        self.headcount.set_recorded_at(recorded_at);
+        self.health.set_recorded_at(recorded_at);
+    }
+
+    fn correct<R: Rng>(&mut self, rng: &mut R) {
+        self.headcount.correct(rng);
+        self.health.recount(self.headcount.quantity);
+    }
+}
+
+// Corrects the headcounts along with their health reports; a headcount whose health report isn't
+// in memory, or was loaded from a snapshot, isn't recounted
+pub fn correct_headcount_records(
+    headcount_reports: &mut [HeadcountReport],
+    health_reports: &mut [HealthReport],
+    config: &LateArrivalConfig,
+    from_when: NaiveDateTime,
+    to_when: NaiveDateTime,
+) {
+    let mut headcounts: HashMap<usize, &mut HeadcountReport> = headcount_reports
+        .iter_mut()
+        .map(|report| (report.id, report))
+        .collect();
+    let mut recounts: Vec<Recount> = health_reports
+        .iter_mut()
+        .filter_map(|health| {
+            let headcount = headcounts.remove(&health.headcount_report_id?)?;
+            Some(Recount { headcount, health })
+        })
+        .collect();
+    correct_records(&mut recounts, config, from_when, to_when);
+}
+
 pub fn expand_health_report_vec_for_headcount_vec(
-    health_reports: &mut Vec<HealthReport>,
+    health_reports: &mut Table<HealthReport>,
     headcount_reports: &[HeadcountReport],
-    employees: &Vec<Employee>,
-    herds: &Vec<Herd>,
-    ill_max_pct: f32,
-    severly_ill_max_pct: f32,
-    terminal_max_pct: f32,
+    vets: &Roster,
+    herds: &[Herd],
+    weather: &[Weather],
+    expansion: &Expansion,
 ) {
-    let mut rng = rand::thread_rng();
-    let employee_distribution = Slice::new(employees).unwrap();
-    let ill_distribution = Uniform::new(0.0, ill_max_pct);
-    let severly_ill_distribution = Uniform::new(0.0, severly_ill_max_pct);
-    let terminal_distribution = Uniform::new(0.0, terminal_max_pct);
+    let mut rng = generator_rng();
+    let report_interval = expansion.config.report_interval;
+    let illness_config = &expansion.config.illness;
+    let ill_distribution = Uniform::new(0.0, illness_config.ill_max_pct);
+    let severly_ill_distribution = Uniform::new(0.0, illness_config.severly_ill_max_pct);
+    let terminal_distribution = Uniform::new(0.0, illness_config.terminal_max_pct);
 
     for hc in headcount_reports {
-        let doctor = employee_distribution.sample(&mut rng);
+        // the examination happens during some vet's shift before the next headcount
+        // the herd goes unexamined while there's no vet employed
+        let (vet, timestamp) =
+            match vets.assign(&mut rng, hc.timestamp, hc.timestamp + report_interval) {
+                Some(assignment) => assignment,
+                None => continue,
+            };
         let total_count = hc.quantity;
-        let ill_count = (total_count as f32 * ill_distribution.sample(&mut rng)) as u32;
+        let illness = weather_on(weather, timestamp.date()).map_or(1., Weather::illness_factor);
+        let ill_count = (total_count as f32 * ill_distribution.sample(&mut rng) * illness) as u32;
         let severly_ill_count =
-            (total_count as f32 * severly_ill_distribution.sample(&mut rng)) as u32;
+            (total_count as f32 * severly_ill_distribution.sample(&mut rng) * illness) as u32;
         let terminal_count = (total_count as f32 * terminal_distribution.sample(&mut rng)) as u32;
+        // bad enough weather could make more of the herd ill than there are animals, so the worse
+        // states are taken first, and the milder ones only from what they left
+        let severly_ill_count = severly_ill_count.min(total_count - terminal_count);
+        let ill_count = ill_count.min(total_count - terminal_count - severly_ill_count);
         let healthy_count = total_count - ill_count - severly_ill_count - terminal_count;
         health_reports.push(HealthReport::new(
-            health_reports.len(),
-            doctor,
+            health_reports.next_id(),
+            vet,
             &herds[hc.herd_id],
-            hc.timestamp,
-            healthy_count,
-            ill_count,
-            severly_ill_count,
-            terminal_count,
+            hc,
+            timestamp,
+            HealthCounts {
+                healthy: healthy_count,
+                ill: ill_count,
+                severly_ill: severly_ill_count,
+                terminal: terminal_count,
+            },
         ))
     }
 }
@@ -1,38 +1,104 @@
-use crate::{pasture::Pasture, species::Species};
-use rand::{distributions::Slice, prelude::Distribution};
-use serde::Serialize;
+use crate::{
+    parallel::generator_rng,
+    pasture::{Pasture, PastureKind},
+    species::Species,
+};
+use rand::{
+    distributions::Slice,
+    prelude::{Distribution, IteratorRandom},
+    Rng,
+};
+use serde::{Deserialize, Serialize};
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize)]
 pub struct Herd {
     pub id: usize,
     pub pasture_id: usize,
     pub species_id: usize,
+    pub area: f32,       // the part of the pasture the herd is allotted
+    pub capacity: usize, // how many animals fit in the area
 }
 
 impl Herd {
-    pub const fn new(id: usize, pasture: &Pasture, species: &Species) -> Self {
+    pub fn new(id: usize, pasture: &Pasture, species: &Species, area: f32) -> Self {
         Herd {
             id,
             pasture_id: pasture.id,
             species_id: species.id,
+            area,
+            capacity: capacity(pasture, species, area),
         }
     }
 }
 
+fn capacity(pasture: &Pasture, species: &Species, area: f32) -> usize {
+    let area_requirement = species
+        .area_requirements
+        .as_ref()
+        .unwrap()
+        .pasture_kind_to_req_area[pasture.kind];
+    (area / area_requirement) as usize
+}
+
+// A pasture is split between up to the given number of herds, possibly of different species;
+// herds whose share couldn't fit a single animal are dropped and their area left to the others,
+// and a last herd which doesn't fit either is swapped for one of a species that does; pastures too
+// small for any species are left empty. Individual pastures are pens, so they keep a single herd
 pub fn expand_herd_vec(
     herds: &mut Vec<Herd>,
     pastures: &[Pasture],
     species: &[Species],
     species_idxs: &[usize],
+    max_herds_per_pasture: usize,
 ) {
-    let mut rng = rand::thread_rng();
+    let mut rng = generator_rng();
     let species_idxs_distribution = Slice::new(species_idxs).unwrap();
 
     for pasture in pastures.iter() {
-        herds.push(Herd::new(
-            herds.len(),
-            pasture,
-            &species[*species_idxs_distribution.sample(&mut rng)],
-        ));
+        let herd_count = match pasture.kind {
+            PastureKind::Individual => 1,
+            _ => rng.gen_range(1..=max_herds_per_pasture.max(1)),
+        };
+        let mut shares: Vec<(&Species, f32)> = (0..herd_count)
+            .map(|_| {
+                (
+                    &species[*species_idxs_distribution.sample(&mut rng)],
+                    rng.gen_range(1.0..2.0),
+                )
+            })
+            .collect();
+        loop {
+            let total_weight: f32 = shares.iter().map(|(_, weight)| weight).sum();
+            let overstocked = shares.iter().position(|(species, weight)| {
+                capacity(pasture, species, pasture.area * weight / total_weight) == 0
+            });
+            match overstocked {
+                Some(idx) if shares.len() > 1 => {
+                    shares.remove(idx);
+                }
+                Some(_) => {
+                    let fitting = species_idxs
+                        .iter()
+                        .map(|&idx| &species[idx])
+                        .filter(|species| capacity(pasture, species, pasture.area) > 0)
+                        .choose(&mut rng);
+                    match fitting {
+                        Some(species) => shares[0].0 = species,
+                        None => break,
+                    }
+                }
+                _ => {
+                    for (species, weight) in shares.iter() {
+                        herds.push(Herd::new(
+                            herds.len(),
+                            pasture,
+                            species,
+                            pasture.area * weight / total_weight,
+                        ));
+                    }
+                    break;
+                }
+            }
+        }
     }
 }
@@ -0,0 +1,115 @@
+use std::str::FromStr;
+
+use chrono::{Duration, NaiveDateTime};
+use rand::{seq::index, Rng};
+
+use crate::parallel::generator_rng;
+
+pub enum LatenessDistribution {
+    Uniform { max: Duration },
+    Exponential { mean: Duration },
+}
+
+impl LatenessDistribution {
+    fn sample<R: Rng>(&self, rng: &mut R) -> Duration {
+        match self {
+            LatenessDistribution::Uniform { max } => {
+                Duration::seconds(rng.gen_range(0..=max.num_seconds()))
+            }
+            LatenessDistribution::Exponential { mean } => {
+                let u: f64 = rng.gen_range(0.0..1.0);
+                Duration::seconds((-(1.0 - u).ln() * mean.num_seconds() as f64) as i64)
+            }
+        }
+    }
+}
+
+// Parses `<exponential|uniform>:<days>`, the days being the mean or the maximum lateness
+impl FromStr for LatenessDistribution {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, days) = s
+            .split_once(':')
+            .ok_or_else(|| format!("expected <exponential|uniform>:<days>, got {}", s))?;
+        let days: f64 = days
+            .parse()
+            .ok()
+            .filter(|&days: &f64| days >= 0.)
+            .ok_or_else(|| format!("the lateness isn't a number of days: {}", days))?;
+        let lateness = Duration::seconds((days * 86_400.) as i64);
+        match kind {
+            "exponential" => Ok(LatenessDistribution::Exponential { mean: lateness }),
+            "uniform" => Ok(LatenessDistribution::Uniform { max: lateness }),
+            _ => Err(format!("unknown lateness distribution: {}", kind)),
+        }
+    }
+}
+
+pub struct LateArrivalConfig {
+    pub late_pct: f32,
+    pub lateness: LatenessDistribution,
+    pub correction_pct: f32,
+}
+
+// A record whose arrival in the source system may lag behind the moment it describes. When it
+// arrived isn't part of the source tables, whose schema is that of the model; an ETL only sees it
+// as the snapshot the record first shows up in
+pub trait LateArriving {
+    fn occurred_at(&self) -> NaiveDateTime;
+    fn recorded_at(&self) -> NaiveDateTime;
+    fn set_recorded_at(&mut self, recorded_at: NaiveDateTime);
+    fn correct<R: Rng>(&mut self, rng: &mut R);
+}
+
+pub fn delay_records<T: LateArriving>(records: &mut [T], config: &LateArrivalConfig) {
+    let mut rng = generator_rng();
+
+    let late_count = (records.len() as f32 * config.late_pct) as usize;
+    for idx in index::sample(&mut rng, records.len(), late_count) {
+        let record = &mut records[idx];
+        let recorded_at = record.occurred_at() + config.lateness.sample(&mut rng);
+        record.set_recorded_at(recorded_at);
+    }
+}
+
+pub fn correct_records<T: LateArriving>(
+    records: &mut [T],
+    config: &LateArrivalConfig,
+    from_when: NaiveDateTime,
+    to_when: NaiveDateTime,
+) {
+    let mut rng = generator_rng();
+
+    let span = to_when.signed_duration_since(from_when).num_seconds();
+    let correction_count = (records.len() as f32 * config.correction_pct) as usize;
+    for idx in index::sample(&mut rng, records.len(), correction_count) {
+        let record = &mut records[idx];
+        record.correct(&mut rng);
+        let corrected_at = from_when + Duration::seconds(rng.gen_range(0..=span));
+        if corrected_at > record.recorded_at() {
+            record.set_recorded_at(corrected_at);
+        }
+    }
+}
+
+pub fn arrived_by<T: LateArriving>(records: &[T], when: Option<NaiveDateTime>) -> Vec<&T> {
+    records
+        .iter()
+        .filter(|record| when.is_none_or(|when| record.recorded_at() <= when))
+        .collect()
+}
+
+// What a record loaded from a snapshot starts out with, until mark_arrived sets it
+pub fn unknown_arrival() -> NaiveDateTime {
+    NaiveDateTime::from_timestamp(0, 0)
+}
+
+// The records saved in a snapshot had all arrived by the time it was taken, so they're treated as
+// if they had arrived on time
+pub fn mark_arrived<T: LateArriving>(records: &mut [T]) {
+    for record in records.iter_mut() {
+        let occurred_at = record.occurred_at();
+        record.set_recorded_at(occurred_at);
+    }
+}
@@ -1,25 +1,126 @@
-use std::time::Duration;
-
 use chrono::NaiveDateTime;
-use rand::{distributions::Uniform, prelude::Distribution, seq::index, Rng};
-use serde::Serialize;
+use rand::{seq::SliceRandom, Rng};
+use serde::{de, Deserialize, Deserializer, Serialize};
+
+use crate::{
+    herd::Herd,
+    parallel::{entity_rng, generator_rng, par_map},
+    species::Species,
+    table::Table,
+};
+
+const LIVESTOCK_MARKETS: [&str; 4] = [
+    "Łowicz Livestock Market",
+    "Sokółka Livestock Market",
+    "Skaryszew Horse and Cattle Fair",
+    "Końskie Livestock Auction",
+];
+const FARMS: [&str; 5] = [
+    "Zalesie Farm",
+    "Dąbrowa Farm",
+    "Nowa Wieś Farm",
+    "Wólka Farm",
+    "Kamionka Farm",
+];
+const BUTCHERY: &str = "Farm Butchery";
+const RENDERING_PLANT: &str = "Rendering Plant";
 
-use crate::{herd::Herd, pasture::Pasture, species::Species};
+// One of the names above; behind an alias, so serde doesn't take the field for a borrowed string
+pub type Destination = &'static str;
 
-#[derive(Serialize)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum DisposalPurpose {
     Butcher,
     Health,
+    SaleAlive,
+    Transfer,
+    NaturalDeath,
+    Accident,
+    PredatorLoss,
+}
+
+impl DisposalPurpose {
+    // Where the animal ends up; nothing is left of the ones taken by predators
+    fn random_destination<R: Rng>(&self, rng: &mut R) -> Option<Destination> {
+        match self {
+            DisposalPurpose::Butcher => Some(BUTCHERY),
+            DisposalPurpose::SaleAlive => LIVESTOCK_MARKETS.choose(rng).copied(),
+            DisposalPurpose::Transfer => FARMS.choose(rng).copied(),
+            DisposalPurpose::Health | DisposalPurpose::NaturalDeath | DisposalPurpose::Accident => {
+                Some(RENDERING_PLANT)
+            }
+            DisposalPurpose::PredatorLoss => None,
+        }
+    }
+
+    // Only the animals that leave the farm alive or end up butchered are worth anything
+    const fn is_priced(&self) -> bool {
+        matches!(self, DisposalPurpose::Butcher | DisposalPurpose::SaleAlive)
+    }
+}
+
+// The loaded destinations are mapped back to the names they were generated from
+fn deserialize_destination<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Destination>, D::Error> {
+    let destination: Option<String> = Option::deserialize(deserializer)?;
+    destination
+        .map(|destination| {
+            LIVESTOCK_MARKETS
+                .iter()
+                .chain(FARMS.iter())
+                .chain([BUTCHERY, RENDERING_PLANT].iter())
+                .find(|&&known| known == destination)
+                .copied()
+                .ok_or_else(|| de::Error::custom(format!("unknown destination {}", destination)))
+        })
+        .transpose()
+}
+
+// How the animals of a species leave the farm before reaching the end of their lifespan, and what
+// they weigh and are worth when they do
+pub struct DisposalProfile {
+    pub species_id: usize,
+    pub early_disposal_pcts: Vec<(DisposalPurpose, f32)>, // chance of each purpose per lifespan
+    pub weight_at_birth: f32,                             // in kilograms
+    pub weight_at_lifespan: f32,
+    pub price_per_kg: f32,
+}
+
+impl DisposalProfile {
+    // The chances are per lifespan, an animal only part of which is covered gets a part of them
+    fn random_early_purpose<R: Rng>(&self, rng: &mut R, exposure: f32) -> Option<DisposalPurpose> {
+        let mut roll = rng.gen_range(0.0..1.0);
+        for &(purpose, pct) in self.early_disposal_pcts.iter() {
+            let pct = pct * exposure;
+            if roll < pct {
+                return Some(purpose);
+            }
+            roll -= pct;
+        }
+        None
+    }
+
+    // Animals gain weight evenly over their lifespan
+    fn weight(&self, species: &Species, age: chrono::Duration) -> f32 {
+        let lifespan = chrono::Duration::from_std(species.lifespan).unwrap();
+        let grown_pct = (age.num_seconds() as f32 / lifespan.num_seconds() as f32).clamp(0., 1.);
+        self.weight_at_birth + (self.weight_at_lifespan - self.weight_at_birth) * grown_pct
+    }
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize)]
 pub struct Livestock {
     pub id: usize,
-    birth: NaiveDateTime,
-    disposal: Option<NaiveDateTime>,
-    disposal_purpose: Option<DisposalPurpose>,
-    species_id: usize,
-    herd_id: usize,
+    pub birth: NaiveDateTime,
+    pub disposal: Option<NaiveDateTime>,
+    pub disposal_purpose: Option<DisposalPurpose>,
+    pub species_id: usize,
+    pub herd_id: usize,
+    pub disposal_weight: Option<f32>,
+    pub disposal_price: Option<f32>,
+    #[serde(deserialize_with = "deserialize_destination")]
+    pub disposal_destination: Option<Destination>,
 }
 
 impl Livestock {
@@ -37,95 +138,170 @@ impl Livestock {
             disposal_purpose,
             species_id: herd.species_id,
             herd_id: herd.id,
+            disposal_weight: None,
+            disposal_price: None,
+            disposal_destination: None,
         }
     }
+
+    fn dispose<R: Rng>(
+        &mut self,
+        rng: &mut R,
+        when: NaiveDateTime,
+        purpose: DisposalPurpose,
+        species: &Species,
+        profile: Option<&DisposalProfile>,
+    ) {
+        self.disposal = Some(when);
+        self.disposal_purpose = Some(purpose);
+        self.disposal_destination = purpose.random_destination(rng);
+        if let Some(profile) = profile {
+            if purpose != DisposalPurpose::PredatorLoss {
+                let weight = profile.weight(species, when.signed_duration_since(self.birth));
+                self.disposal_weight = Some((weight * 10.).round() / 10.);
+                if purpose.is_priced() {
+                    self.disposal_price =
+                        Some((weight * profile.price_per_kg * 100.).round() / 100.);
+                }
+            }
+        }
+    }
+
+    pub fn is_alive_at(&self, moment: NaiveDateTime) -> bool {
+        self.birth <= moment && self.disposal.is_none_or(|disposal| disposal > moment)
+    }
+}
+
+// The animals stay sorted by their ids when the ones disposed of earlier are dropped, so one is
+// found by a binary search
+pub fn livestock_position(livestock: &[Livestock], id: usize) -> usize {
+    livestock
+        .binary_search_by_key(&id, |animal| animal.id)
+        .expect("the animal was dropped")
 }
 
+// Every herd is stocked up to the given fraction of its capacity: each place in the herd is taken
+// by an animal, which gets replaced by a newborn once it reaches its lifespan and is butchered; the
+// places stay with the herd the animal was born into, wherever it has been moved since, so that
+// rotations don't change the size of the stock;
+// places left empty are filled with animals born over the first lifespan, as far as it falls within
+// the given dates, so herds fill up at the same pace however the dates are split into expansions
 pub fn expand_livestock(
-    livestock: &mut Vec<Livestock>,
-    herds: &Vec<Herd>,
-    species: &Vec<Species>,
-    pastures: &Vec<Pasture>,
+    livestock: &mut Table<Livestock>,
+    herds: &[Herd],
+    species: &[Species],
+    stocking_pct: f32,
     earliest_birth: NaiveDateTime,
     latest_birth: NaiveDateTime,
 ) {
-    let mut rng = rand::thread_rng();
-
-    let birth_span = latest_birth
-        .signed_duration_since(earliest_birth)
-        .to_std()
-        .unwrap();
-    let birth_offset_distribution = Uniform::new(std::time::Duration::new(0, 0), birth_span);
-
-    for herd in herds {
-        let species = &species[herd.species_id];
-        let pasture = &pastures[herd.pasture_id];
-        let area_requirement = species
-            .area_requirements
-            .as_ref()
-            .unwrap()
-            .pasture_kind_to_req_area[pasture.kind];
-        let count = (pasture.area
-            / (area_requirement
-                * chrono::Duration::from_std(species.lifespan)
-                    .unwrap()
-                    .num_days() as f32)) as usize;
-        for _ in 0..count {
-            let birth_offset =
-                chrono::Duration::from_std(birth_offset_distribution.sample(&mut rng)).unwrap();
-            livestock.push(Livestock::new(
-                livestock.len(),
-                earliest_birth + birth_offset,
-                None,
-                None,
-                herd,
-            ));
+    let seed = generator_rng().gen();
+
+    // the next birth in every place which is already taken
+    let mut next_births: Vec<Vec<NaiveDateTime>> = herds.iter().map(|_| vec![]).collect();
+    for animal in livestock.iter() {
+        if animal.is_alive_at(earliest_birth) {
+            let lifespan = chrono::Duration::from_std(species[animal.species_id].lifespan).unwrap();
+            next_births[animal.herd_id].push(animal.birth + lifespan);
+        }
+    }
+
+    // herds are stocked independently of each other, so they're generated in parallel
+    let herd_births = par_map(herds, |herd| {
+        let mut rng = entity_rng(seed, herd.id);
+        let target_count = ((herd.capacity as f32 * stocking_pct).round() as usize).max(1);
+        let lifespan = chrono::Duration::from_std(species[herd.species_id].lifespan).unwrap();
+        let mut next_births = next_births[herd.id].clone();
+        for _ in next_births.len()..target_count {
+            let offset = chrono::Duration::seconds(rng.gen_range(0..lifespan.num_seconds()));
+            next_births.push(earliest_birth + offset);
+        }
+        let mut births = vec![];
+        for mut birth in next_births {
+            while birth < latest_birth {
+                births.push(birth);
+                birth += lifespan;
+            }
+        }
+        births
+    });
+    for (herd, births) in herds.iter().zip(herd_births) {
+        for birth in births {
+            livestock.push(Livestock::new(livestock.next_id(), birth, None, None, herd));
         }
     }
 }
 
+// Animals of species with a disposal profile leave early at the profile's rates, the others live
+// out their lifespan; the rates are per lifespan, so splitting the dates into several expansions
+// leaves them the same
 pub fn kill_off_livestock_vec(
-    livestock: &mut Vec<Livestock>,
-    kill_pct: f32,
-    species: &Vec<Species>,
+    livestock: &mut [Livestock],
+    species: &[Species],
+    profiles: &[DisposalProfile],
+    min_date: NaiveDateTime,
     max_date: NaiveDateTime,
 ) {
-    let mut rng = rand::thread_rng();
+    let mut rng = generator_rng();
 
-    let kill_count = (livestock.len() as f32 * kill_pct) as usize;
-    let to_kill = index::sample(&mut rng, livestock.len(), kill_count);
-    for id in to_kill {
-        let animal = &mut livestock[id];
-        if let Some(_) = animal.disposal {
+    for animal in livestock.iter_mut() {
+        if animal.disposal.is_some() {
             continue;
         }
         let species = &species[animal.species_id];
-        let lifespan =
-            chrono::Duration::from_std(rng.gen_range(Duration::new(0, 0)..species.lifespan))
-                .unwrap();
-        let mut disposal_time = animal.birth + lifespan;
-        if disposal_time > max_date {
-            disposal_time = max_date;
+        let lifespan = chrono::Duration::from_std(species.lifespan).unwrap();
+        // the history before the given dates is already settled
+        let first_moment = animal.birth.max(min_date);
+        let last_moment = (animal.birth + lifespan).min(max_date);
+        if first_moment >= last_moment {
+            continue;
         }
-        animal.disposal = Some(disposal_time);
-        animal.disposal_purpose = Some(DisposalPurpose::Health);
+        let exposure = (last_moment
+            .signed_duration_since(first_moment)
+            .num_seconds() as f32
+            / lifespan.num_seconds() as f32)
+            .min(1.);
+        let profile = species_profile(profiles, animal.species_id);
+        let purpose = match profile.and_then(|p| p.random_early_purpose(&mut rng, exposure)) {
+            Some(purpose) => purpose,
+            None => continue,
+        };
+        let span = last_moment
+            .signed_duration_since(first_moment)
+            .num_seconds();
+        let disposal_time = first_moment + chrono::Duration::seconds(rng.gen_range(0..span));
+        animal.dispose(&mut rng, disposal_time, purpose, species, profile);
     }
 }
 
 pub fn butcher_livestock_vec(
-    livestock: &mut Vec<Livestock>,
-    species: &Vec<Species>,
+    livestock: &mut [Livestock],
+    species: &[Species],
+    profiles: &[DisposalProfile],
     cutoff_time: NaiveDateTime,
 ) {
+    let mut rng = generator_rng();
+
     for animal in livestock {
-        if let Some(_) = animal.disposal_purpose {
+        if animal.disposal_purpose.is_some() {
             continue;
         }
         let species = &species[animal.species_id];
         let disposal = animal.birth + chrono::Duration::from_std(species.lifespan).unwrap();
         if disposal < cutoff_time {
-            animal.disposal = Some(disposal);
-            animal.disposal_purpose = Some(DisposalPurpose::Butcher);
+            let profile = species_profile(profiles, animal.species_id);
+            animal.dispose(
+                &mut rng,
+                disposal,
+                DisposalPurpose::Butcher,
+                species,
+                profile,
+            );
         }
     }
 }
+
+fn species_profile(profiles: &[DisposalProfile], species_id: usize) -> Option<&DisposalProfile> {
+    profiles
+        .iter()
+        .find(|profile| profile.species_id == species_id)
+}
@@ -0,0 +1,349 @@
+use std::collections::{BTreeSet, HashMap};
+
+use chrono::{Duration, NaiveDateTime};
+use rand::{prelude::SliceRandom, Rng};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    herd::Herd,
+    livestock::{livestock_position, DisposalPurpose, Livestock},
+    parallel::generator_rng,
+    pasture::{Pasture, PastureKind},
+    table::Table,
+};
+
+const ROTATION_MIN_DAYS: i64 = 21;
+const ROTATION_MAX_DAYS: i64 = 42;
+const ISOLATION_MIN_DAYS: i64 = 7;
+const ISOLATION_MAX_DAYS: i64 = 14;
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum MovementReason {
+    RotationalGrazing,
+    Isolation,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct LivestockMovement {
+    id: usize,
+    pub livestock_id: usize,
+    pub timestamp: NaiveDateTime,
+    from_herd_id: usize,
+    pub to_herd_id: usize,
+    pub reason: MovementReason,
+}
+
+impl LivestockMovement {
+    pub const fn new(
+        id: usize,
+        animal: &Livestock,
+        timestamp: NaiveDateTime,
+        from_herd: &Herd,
+        to_herd: &Herd,
+        reason: MovementReason,
+    ) -> Self {
+        LivestockMovement {
+            id,
+            livestock_id: animal.id,
+            timestamp,
+            from_herd_id: from_herd.id,
+            to_herd_id: to_herd.id,
+            reason,
+        }
+    }
+}
+
+// A time an animal spent in a herd, until it moved on or was disposed of
+struct Stay<'a> {
+    start: NaiveDateTime,
+    end: Option<NaiveDateTime>,
+    animal: &'a Livestock,
+}
+
+impl Stay<'_> {
+    fn is_over_at(&self, moment: NaiveDateTime) -> bool {
+        self.end.is_some_and(|end| end <= moment)
+    }
+}
+
+// Which herd every animal belongs to at any time; an animal starts in the herd it was born into
+// and follows its movements
+pub struct HerdMembership<'a> {
+    livestock: &'a [Livestock],
+    animal_herds: Vec<Vec<(NaiveDateTime, usize)>>, // per animal position, its herds and since when
+    herd_stays: Vec<Vec<Stay<'a>>>,                 // per herd, sorted by their start
+    herd_stay_ends: Vec<Vec<NaiveDateTime>>,        // per herd, sorted
+}
+
+impl<'a> HerdMembership<'a> {
+    pub fn new(
+        livestock: &'a [Livestock],
+        movements: &[LivestockMovement],
+        herds: &[Herd],
+    ) -> Self {
+        let mut animal_herds: Vec<Vec<(NaiveDateTime, usize)>> = livestock
+            .iter()
+            .map(|animal| vec![(animal.birth, animal.herd_id)])
+            .collect();
+        for movement in movements {
+            let position = livestock_position(livestock, movement.livestock_id);
+            animal_herds[position].push((movement.timestamp, movement.to_herd_id));
+        }
+        let mut herd_stays: Vec<Vec<Stay>> = herds.iter().map(|_| vec![]).collect();
+        for (animal, herds) in livestock.iter().zip(animal_herds.iter_mut()) {
+            herds.sort_by_key(|(since, _)| *since);
+            for (idx, &(start, herd_id)) in herds.iter().enumerate() {
+                let end = match (herds.get(idx + 1), animal.disposal) {
+                    (Some(&(moved, _)), Some(disposal)) => Some(moved.min(disposal)),
+                    (Some(&(moved, _)), None) => Some(moved),
+                    (None, disposal) => disposal,
+                };
+                herd_stays[herd_id].push(Stay { start, end, animal });
+            }
+        }
+        let mut herd_stay_ends = vec![];
+        for stays in herd_stays.iter_mut() {
+            stays.sort_by_key(|stay| stay.start);
+            let mut ends: Vec<NaiveDateTime> = stays.iter().filter_map(|stay| stay.end).collect();
+            ends.sort();
+            herd_stay_ends.push(ends);
+        }
+        HerdMembership {
+            livestock,
+            animal_herds,
+            herd_stays,
+            herd_stay_ends,
+        }
+    }
+
+    pub const fn livestock(&self) -> &'a [Livestock] {
+        self.livestock
+    }
+
+    pub fn herd_at(&self, animal: &Livestock, moment: NaiveDateTime) -> usize {
+        let herds = &self.animal_herds[livestock_position(self.livestock, animal.id)];
+        let moved_count = herds.partition_point(|(since, _)| *since <= moment);
+        herds[moved_count.max(1) - 1].1
+    }
+
+    // How many living animals belong to the herd at the given moment
+    pub fn count_at(&self, herd_id: usize, moment: NaiveDateTime) -> usize {
+        let started = self.herd_stays[herd_id].partition_point(|stay| stay.start <= moment);
+        let ended = self.herd_stay_ends[herd_id].partition_point(|&end| end <= moment);
+        started - ended
+    }
+
+    // For going through the members of a herd at moments which only move forward
+    pub fn sweep(&self, herd_id: usize) -> MemberSweep<'_, 'a> {
+        MemberSweep {
+            stays: &self.herd_stays[herd_id],
+            next: 0,
+            present: vec![],
+        }
+    }
+}
+
+pub struct MemberSweep<'m, 'a> {
+    stays: &'m [Stay<'a>],
+    next: usize,
+    present: Vec<&'m Stay<'a>>,
+}
+
+impl<'a> MemberSweep<'_, 'a> {
+    // The living animals belonging to the herd at the given moment, which can't be earlier than
+    // the one asked about before
+    pub fn members_at(&mut self, moment: NaiveDateTime) -> Vec<&'a Livestock> {
+        while self.next < self.stays.len() && self.stays[self.next].start <= moment {
+            self.present.push(&self.stays[self.next]);
+            self.next += 1;
+        }
+        self.present.retain(|stay| !stay.is_over_at(moment));
+        self.present.iter().map(|stay| stay.animal).collect()
+    }
+}
+
+// Where every animal is while the movements are being generated, by the animals' positions; the
+// members are kept in order, so that they're moved in the same order every run
+struct Placement {
+    current_herds: Vec<usize>,          // per animal
+    herd_members: Vec<BTreeSet<usize>>, // per herd, including the animals which died in it since
+}
+
+impl Placement {
+    fn new(
+        livestock: &[Livestock],
+        movements: &[LivestockMovement],
+        herds: &[Herd],
+        from_when: NaiveDateTime,
+    ) -> Self {
+        let membership = HerdMembership::new(livestock, movements, herds);
+        let current_herds: Vec<usize> = livestock
+            .iter()
+            .map(|animal| membership.herd_at(animal, from_when))
+            .collect();
+        let mut herd_members: Vec<BTreeSet<usize>> =
+            herds.iter().map(|_| BTreeSet::new()).collect();
+        for (position, animal) in livestock.iter().enumerate() {
+            if animal.disposal.is_none_or(|disposal| disposal > from_when) {
+                herd_members[current_herds[position]].insert(position);
+            }
+        }
+        Placement {
+            current_herds,
+            herd_members,
+        }
+    }
+
+    fn move_animal(
+        &mut self,
+        movements: &mut Table<LivestockMovement>,
+        (position, animal): (usize, &Livestock),
+        timestamp: NaiveDateTime,
+        from_herd: &Herd,
+        to_herd: &Herd,
+        reason: MovementReason,
+    ) {
+        self.herd_members[from_herd.id].remove(&position);
+        self.herd_members[to_herd.id].insert(position);
+        self.current_herds[position] = to_herd.id;
+        movements.push(LivestockMovement::new(
+            movements.next_id(),
+            animal,
+            timestamp,
+            from_herd,
+            to_herd,
+            reason,
+        ));
+    }
+}
+
+enum MovementEvent {
+    Rotation(usize),  // herd id
+    Isolation(usize), // animal position
+}
+
+// Animals on open pastures are regularly moved on to the pasture of another herd of their species,
+// and those that will be put down for their health are isolated in individual pens first
+pub fn expand_livestock_movement_vec(
+    movements: &mut Table<LivestockMovement>,
+    livestock: &[Livestock],
+    herds: &[Herd],
+    pastures: &[Pasture],
+    from_when: NaiveDateTime,
+    to_when: NaiveDateTime,
+) {
+    let mut rng = generator_rng();
+
+    let mut placement = Placement::new(livestock, movements, herds, from_when);
+    let mut herds_by_kind: HashMap<(usize, PastureKind), Vec<&Herd>> = HashMap::new();
+    for herd in herds {
+        herds_by_kind
+            .entry((herd.species_id, pastures[herd.pasture_id].kind))
+            .or_default()
+            .push(herd);
+    }
+
+    let mut events = vec![];
+    for herd in herds {
+        if pastures[herd.pasture_id].kind != PastureKind::Open {
+            continue;
+        }
+        let mut timestamp = from_when + random_days(&mut rng, 0, ROTATION_MAX_DAYS);
+        while timestamp < to_when {
+            events.push((timestamp, MovementEvent::Rotation(herd.id)));
+            timestamp += random_days(&mut rng, ROTATION_MIN_DAYS, ROTATION_MAX_DAYS);
+        }
+    }
+    for (position, animal) in livestock.iter().enumerate() {
+        let disposal = match (animal.disposal, animal.disposal_purpose) {
+            (Some(disposal), Some(DisposalPurpose::Health)) => disposal,
+            _ => continue,
+        };
+        if disposal < from_when || disposal >= to_when {
+            continue;
+        }
+        let timestamp = (disposal - random_days(&mut rng, ISOLATION_MIN_DAYS, ISOLATION_MAX_DAYS))
+            .max(animal.birth)
+            .max(from_when);
+        events.push((timestamp, MovementEvent::Isolation(position)));
+    }
+    events.sort_by_key(|(timestamp, _)| *timestamp);
+
+    for (timestamp, event) in events {
+        match event {
+            // the animals of both herds swap pastures, so the groups stay together
+            MovementEvent::Rotation(herd_id) => {
+                let herd = &herds[herd_id];
+                let other_herds: Vec<&Herd> = herds_by_kind[&(herd.species_id, PastureKind::Open)]
+                    .iter()
+                    .copied()
+                    .filter(|other| other.id != herd_id)
+                    .collect();
+                let other_herd = match other_herds.choose(&mut rng) {
+                    Some(other_herd) => *other_herd,
+                    None => continue,
+                };
+                let living_members = |members: &BTreeSet<usize>| -> Vec<usize> {
+                    members
+                        .iter()
+                        .copied()
+                        .filter(|&position| livestock[position].is_alive_at(timestamp))
+                        .collect()
+                };
+                let leaving = living_members(&placement.herd_members[herd.id]);
+                let arriving = living_members(&placement.herd_members[other_herd.id]);
+                // a swap that would overstock either pasture is called off
+                if leaving.len() > other_herd.capacity || arriving.len() > herd.capacity {
+                    continue;
+                }
+                for (moved, from_herd, to_herd) in
+                    [(leaving, herd, other_herd), (arriving, other_herd, herd)]
+                {
+                    for position in moved {
+                        placement.move_animal(
+                            movements,
+                            (position, &livestock[position]),
+                            timestamp,
+                            from_herd,
+                            to_herd,
+                            MovementReason::RotationalGrazing,
+                        );
+                    }
+                }
+            }
+            MovementEvent::Isolation(position) => {
+                let from_herd = &herds[placement.current_herds[position]];
+                if pastures[from_herd.pasture_id].kind == PastureKind::Individual {
+                    continue;
+                }
+                let free_pens: Vec<&Herd> = herds_by_kind
+                    .get(&(from_herd.species_id, PastureKind::Individual))
+                    .into_iter()
+                    .flatten()
+                    .copied()
+                    .filter(|pen| {
+                        let occupied = placement.herd_members[pen.id]
+                            .iter()
+                            .filter(|&&position| livestock[position].is_alive_at(timestamp))
+                            .count();
+                        occupied < pen.capacity
+                    })
+                    .collect();
+                if let Some(pen) = free_pens.choose(&mut rng) {
+                    placement.move_animal(
+                        movements,
+                        (position, &livestock[position]),
+                        timestamp,
+                        from_herd,
+                        pen,
+                        MovementReason::Isolation,
+                    );
+                }
+            }
+        }
+    }
+}
+
+fn random_days<R: Rng>(rng: &mut R, min: i64, max: i64) -> Duration {
+    Duration::days(rng.gen_range(min..=max))
+}
@@ -0,0 +1,582 @@
+use std::{collections::HashSet, fs, io, path::Path};
+
+use crate::employee::Sex;
+
+const POLISH_MALE_NAMES: &[&str] = &[
+    "Adam",
+    "Adrian",
+    "Aleksander",
+    "Andrzej",
+    "Antoni",
+    "Arkadiusz",
+    "Artur",
+    "Bartłomiej",
+    "Bartosz",
+    "Bogdan",
+    "Bogusław",
+    "Cezary",
+    "Damian",
+    "Daniel",
+    "Dariusz",
+    "Dawid",
+    "Dominik",
+    "Edward",
+    "Emil",
+    "Eryk",
+    "Filip",
+    "Franciszek",
+    "Gabriel",
+    "Grzegorz",
+    "Henryk",
+    "Hubert",
+    "Igor",
+    "Ireneusz",
+    "Jacek",
+    "Jakub",
+    "Jan",
+    "Janusz",
+    "Jarosław",
+    "Jerzy",
+    "Józef",
+    "Julian",
+    "Kacper",
+    "Kamil",
+    "Karol",
+    "Kazimierz",
+    "Konrad",
+    "Krystian",
+    "Krzysztof",
+    "Leszek",
+    "Łukasz",
+    "Maciej",
+    "Marcin",
+    "Marek",
+    "Marian",
+    "Mariusz",
+    "Mateusz",
+    "Michał",
+    "Mieczysław",
+    "Mikołaj",
+    "Miłosz",
+    "Norbert",
+    "Oskar",
+    "Patryk",
+    "Paweł",
+    "Piotr",
+    "Przemysław",
+    "Radosław",
+    "Rafał",
+    "Robert",
+    "Roman",
+    "Ryszard",
+    "Sebastian",
+    "Stanisław",
+    "Stefan",
+    "Szymon",
+    "Sławomir",
+    "Tadeusz",
+    "Tomasz",
+    "Wiesław",
+    "Wiktor",
+    "Witold",
+    "Władysław",
+    "Wojciech",
+    "Zbigniew",
+    "Zdzisław",
+    "Zenon",
+    "Zygmunt",
+];
+const POLISH_FEMALE_NAMES: &[&str] = &[
+    "Ada",
+    "Agata",
+    "Agnieszka",
+    "Aleksandra",
+    "Alicja",
+    "Amelia",
+    "Anna",
+    "Antonina",
+    "Barbara",
+    "Beata",
+    "Bożena",
+    "Danuta",
+    "Dorota",
+    "Edyta",
+    "Elżbieta",
+    "Emilia",
+    "Ewa",
+    "Ewelina",
+    "Gabriela",
+    "Grażyna",
+    "Halina",
+    "Hanna",
+    "Helena",
+    "Irena",
+    "Iwona",
+    "Izabela",
+    "Jadwiga",
+    "Janina",
+    "Joanna",
+    "Jolanta",
+    "Julia",
+    "Justyna",
+    "Kamila",
+    "Karolina",
+    "Katarzyna",
+    "Kinga",
+    "Klaudia",
+    "Krystyna",
+    "Laura",
+    "Lena",
+    "Lidia",
+    "Magdalena",
+    "Maja",
+    "Małgorzata",
+    "Maria",
+    "Marianna",
+    "Marta",
+    "Martyna",
+    "Marzena",
+    "Monika",
+    "Natalia",
+    "Nikola",
+    "Oliwia",
+    "Patrycja",
+    "Paulina",
+    "Renata",
+    "Róża",
+    "Sandra",
+    "Stanisława",
+    "Sylwia",
+    "Teresa",
+    "Urszula",
+    "Wanda",
+    "Weronika",
+    "Wiktoria",
+    "Zofia",
+    "Zuzanna",
+];
+// Adjectival surnames are given in the masculine form, see `feminine_polish_surname`
+const POLISH_SURNAMES: &[&str] = &[
+    "Adamczyk",
+    "Baran",
+    "Błaszczyk",
+    "Borkowski",
+    "Chmielewski",
+    "Czarnecki",
+    "Czerwiński",
+    "Dąbrowski",
+    "Dudek",
+    "Gajewski",
+    "Głowacki",
+    "Górecki",
+    "Górski",
+    "Grabowski",
+    "Jabłoński",
+    "Jakubowski",
+    "Jankowski",
+    "Jasiński",
+    "Jaworski",
+    "Kaczmarek",
+    "Kalinowski",
+    "Kamiński",
+    "Kaźmierczak",
+    "Kołodziej",
+    "Kowalczyk",
+    "Kowalski",
+    "Kozłowski",
+    "Krajewski",
+    "Krawczyk",
+    "Król",
+    "Kubiak",
+    "Kwiatkowski",
+    "Lewandowski",
+    "Lis",
+    "Majewski",
+    "Makowski",
+    "Malinowski",
+    "Marciniak",
+    "Mazur",
+    "Michalski",
+    "Mróz",
+    "Nowak",
+    "Nowakowski",
+    "Nowicki",
+    "Olszewski",
+    "Ostrowski",
+    "Pawlak",
+    "Pawłowski",
+    "Piątek",
+    "Pietrzak",
+    "Piotrowski",
+    "Przybylski",
+    "Rutkowski",
+    "Sadowski",
+    "Sikora",
+    "Sobczak",
+    "Sokołowski",
+    "Stępień",
+    "Szczepański",
+    "Szewczyk",
+    "Szulc",
+    "Szymański",
+    "Szymczak",
+    "Tomaszewski",
+    "Urbański",
+    "Walczak",
+    "Wasilewski",
+    "Wieczorek",
+    "Wilk",
+    "Wiśniewski",
+    "Witkowski",
+    "Włodarczyk",
+    "Wojciechowski",
+    "Wójcik",
+    "Wróbel",
+    "Wróblewski",
+    "Wysocki",
+    "Zakrzewski",
+    "Zalewski",
+    "Zając",
+    "Zawadzki",
+    "Zieliński",
+    "Ziółkowski",
+];
+
+const ENGLISH_MALE_NAMES: &[&str] = &[
+    "Aaron",
+    "Adam",
+    "Alan",
+    "Albert",
+    "Alexander",
+    "Andrew",
+    "Anthony",
+    "Arthur",
+    "Benjamin",
+    "Brandon",
+    "Brian",
+    "Bruce",
+    "Carl",
+    "Charles",
+    "Christopher",
+    "Daniel",
+    "David",
+    "Dennis",
+    "Donald",
+    "Douglas",
+    "Edward",
+    "Eric",
+    "Frank",
+    "Gary",
+    "George",
+    "Gerald",
+    "Gregory",
+    "Harold",
+    "Henry",
+    "Jack",
+    "Jacob",
+    "James",
+    "Jason",
+    "Jeffrey",
+    "Jeremy",
+    "John",
+    "Jonathan",
+    "Joseph",
+    "Joshua",
+    "Justin",
+    "Keith",
+    "Kenneth",
+    "Kevin",
+    "Larry",
+    "Lawrence",
+    "Mark",
+    "Matthew",
+    "Michael",
+    "Nathan",
+    "Nicholas",
+    "Patrick",
+    "Paul",
+    "Peter",
+    "Philip",
+    "Raymond",
+    "Richard",
+    "Robert",
+    "Roger",
+    "Ronald",
+    "Ryan",
+    "Samuel",
+    "Scott",
+    "Sean",
+    "Stephen",
+    "Steven",
+    "Terry",
+    "Thomas",
+    "Timothy",
+    "Walter",
+    "William",
+];
+const ENGLISH_FEMALE_NAMES: &[&str] = &[
+    "Abigail",
+    "Alice",
+    "Amanda",
+    "Amy",
+    "Andrea",
+    "Angela",
+    "Anna",
+    "Ashley",
+    "Barbara",
+    "Betty",
+    "Brenda",
+    "Carol",
+    "Caroline",
+    "Catherine",
+    "Charlotte",
+    "Christine",
+    "Deborah",
+    "Diana",
+    "Donna",
+    "Dorothy",
+    "Elizabeth",
+    "Emily",
+    "Emma",
+    "Evelyn",
+    "Frances",
+    "Grace",
+    "Hannah",
+    "Heather",
+    "Helen",
+    "Jacqueline",
+    "Janet",
+    "Jennifer",
+    "Jessica",
+    "Joan",
+    "Judith",
+    "Julia",
+    "Julie",
+    "Karen",
+    "Katherine",
+    "Kathleen",
+    "Kelly",
+    "Laura",
+    "Lauren",
+    "Linda",
+    "Lisa",
+    "Margaret",
+    "Maria",
+    "Marie",
+    "Mary",
+    "Megan",
+    "Melissa",
+    "Michelle",
+    "Nancy",
+    "Nicole",
+    "Olivia",
+    "Pamela",
+    "Rachel",
+    "Rebecca",
+    "Rose",
+    "Ruth",
+    "Samantha",
+    "Sandra",
+    "Sarah",
+    "Sharon",
+    "Sophie",
+    "Stephanie",
+    "Susan",
+    "Teresa",
+    "Victoria",
+    "Virginia",
+];
+const ENGLISH_SURNAMES: &[&str] = &[
+    "Adams",
+    "Allen",
+    "Anderson",
+    "Bailey",
+    "Baker",
+    "Barnes",
+    "Bell",
+    "Bennett",
+    "Brooks",
+    "Brown",
+    "Butler",
+    "Campbell",
+    "Carter",
+    "Clark",
+    "Collins",
+    "Cook",
+    "Cooper",
+    "Cox",
+    "Davies",
+    "Davis",
+    "Edwards",
+    "Evans",
+    "Fisher",
+    "Foster",
+    "Gray",
+    "Green",
+    "Griffiths",
+    "Hall",
+    "Harris",
+    "Hill",
+    "Hughes",
+    "Jackson",
+    "James",
+    "Johnson",
+    "Jones",
+    "Kelly",
+    "King",
+    "Lee",
+    "Lewis",
+    "Marshall",
+    "Martin",
+    "Mason",
+    "Miller",
+    "Mitchell",
+    "Moore",
+    "Morgan",
+    "Morris",
+    "Murphy",
+    "Parker",
+    "Phillips",
+    "Price",
+    "Reed",
+    "Richardson",
+    "Roberts",
+    "Robinson",
+    "Rogers",
+    "Russell",
+    "Scott",
+    "Shaw",
+    "Smith",
+    "Stewart",
+    "Taylor",
+    "Thomas",
+    "Thompson",
+    "Turner",
+    "Walker",
+    "Ward",
+    "Watson",
+    "White",
+    "Williams",
+    "Wilson",
+    "Wood",
+    "Wright",
+    "Young",
+];
+
+#[derive(Clone, Copy)]
+pub enum SurnameGender {
+    Invariant,
+    Polish,
+}
+
+pub struct NamePack {
+    pub male_names: Vec<String>,
+    pub female_names: Vec<String>,
+    pub surnames: Vec<String>,
+    pub surname_gender: SurnameGender,
+}
+
+impl NamePack {
+    pub fn new(
+        male_names: &[&str],
+        female_names: &[&str],
+        surnames: &[&str],
+        surname_gender: SurnameGender,
+    ) -> Self {
+        NamePack {
+            male_names: male_names.iter().map(|name| name.to_string()).collect(),
+            female_names: female_names.iter().map(|name| name.to_string()).collect(),
+            surnames: surnames.iter().map(|name| name.to_string()).collect(),
+            surname_gender,
+        }
+    }
+
+    pub fn polish() -> Self {
+        NamePack::new(
+            POLISH_MALE_NAMES,
+            POLISH_FEMALE_NAMES,
+            POLISH_SURNAMES,
+            SurnameGender::Polish,
+        )
+    }
+
+    pub fn english() -> Self {
+        NamePack::new(
+            ENGLISH_MALE_NAMES,
+            ENGLISH_FEMALE_NAMES,
+            ENGLISH_SURNAMES,
+            SurnameGender::Invariant,
+        )
+    }
+
+    // One of the packs built in, by its name
+    pub fn built_in(name: &str) -> Option<Self> {
+        match name {
+            "polish" => Some(NamePack::polish()),
+            "english" => Some(NamePack::english()),
+            _ => None,
+        }
+    }
+
+    // Each file holds one name per line, blank lines are skipped
+    pub fn from_files<P: AsRef<Path>>(
+        male_names_path: P,
+        female_names_path: P,
+        surnames_path: P,
+        surname_gender: SurnameGender,
+    ) -> io::Result<Self> {
+        Ok(NamePack {
+            male_names: read_name_list(male_names_path)?,
+            female_names: read_name_list(female_names_path)?,
+            surnames: read_name_list(surnames_path)?,
+            surname_gender,
+        })
+    }
+
+    // How many different full names the pack gives, not telling apart the feminine surnames
+    pub fn full_name_count(&self) -> usize {
+        let distinct = |names: &[String]| names.iter().collect::<HashSet<_>>().len();
+        (distinct(&self.male_names) + distinct(&self.female_names)) * distinct(&self.surnames)
+    }
+
+    pub fn names(&self, sex: Sex) -> &[String] {
+        match sex {
+            Sex::Male => &self.male_names,
+            Sex::Female => &self.female_names,
+        }
+    }
+
+    pub fn surname_for(&self, surname: &str, sex: Sex) -> String {
+        match (self.surname_gender, sex) {
+            (SurnameGender::Polish, Sex::Female) => feminine_polish_surname(surname),
+            _ => surname.to_string(),
+        }
+    }
+}
+
+// Adjectival surnames (-ski, -cki, -dzki, -ny, ...) change their ending, nominal ones don't
+fn feminine_polish_surname(surname: &str) -> String {
+    if surname.ends_with("ki") || surname.ends_with("ny") || surname.ends_with("wy") {
+        let mut feminine = surname[..surname.len() - 1].to_string();
+        feminine.push('a');
+        feminine
+    } else {
+        surname.to_string()
+    }
+}
+
+fn read_name_list<P: AsRef<Path>>(path: P) -> io::Result<Vec<String>> {
+    let names: Vec<String> = fs::read_to_string(&path)?
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect();
+    if names.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} has no names", path.as_ref().display()),
+        ));
+    }
+    Ok(names)
+}
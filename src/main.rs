@@ -1,15 +1,56 @@
+mod aggregate;
+mod bank_account;
+mod calendar;
+mod crop;
+mod customer;
 mod employee;
 mod feeding_report;
+mod field;
 mod headcount_report;
 mod health_report;
 mod herd;
+mod late_arrival;
 mod livestock;
+mod livestock_movement;
+mod locale;
+mod parallel;
 mod pasture;
+mod payroll;
+mod pesel;
+mod product;
+mod production_report;
+mod purchase_order;
+mod report_stream;
+mod sale;
+mod scale;
+mod shift;
 mod snapshot;
 mod species;
+mod star_schema;
+mod stock_movement;
+mod supplier;
+mod table;
+mod treatment;
 mod warehouse;
+mod weather;
 
+use crate::bank_account::*;
+use crate::calendar::*;
+use crate::customer::*;
+use crate::employee::*;
+use crate::health_report::*;
+use crate::late_arrival::*;
+use crate::livestock::*;
+use crate::locale::*;
+use crate::parallel::*;
 use crate::pasture::*;
+use crate::payroll::*;
+use crate::product::*;
+use crate::production_report::*;
+use crate::purchase_order::*;
+use crate::report_stream::*;
+use crate::sale::*;
+use crate::scale::*;
 use crate::snapshot::*;
 use crate::species::*;
 use crate::warehouse::*;
@@ -17,13 +58,129 @@ use chrono::{Duration, Local};
 use enum_map::enum_map;
 
 fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    // `--scale <factor>` multiplies the counts of pastures, employees and warehouses, and the
+    // length of the history
+    let scale: f32 = args
+        .iter()
+        .position(|arg| arg == "--scale")
+        .map(|idx| {
+            args.get(idx + 1)
+                .and_then(|factor| factor.parse().ok())
+                .filter(|&factor: &f32| factor.is_finite() && factor > 0.)
+                .unwrap_or_else(|| usage_error("--scale takes a positive number"))
+        })
+        .unwrap_or(1.);
+    // `--seed <number>` makes the same data come out of every run on the same day, however many
+    // threads generate it; without it, every run is different
+    if let Some(idx) = args.iter().position(|arg| arg == "--seed") {
+        let seed = args
+            .get(idx + 1)
+            .and_then(|seed| seed.parse().ok())
+            .unwrap_or_else(|| usage_error("--seed takes a whole number"));
+        seed_generators(seed);
+    }
+    // `--dry-run` only prints how much data the scale would give
+    if args.iter().any(|arg| arg == "--dry-run") {
+        print_estimates(scale);
+        return;
+    }
+    // `--star-schema` also exports the star schema expected from the ETL of every snapshot
+    let star_schema = args.iter().any(|arg| arg == "--star-schema");
+    // `--no-dim-time` leaves out the time of day dimension, which is the same in every snapshot
+    let dim_time = !args.iter().any(|arg| arg == "--no-dim-time");
+    // `--stream` generates the history in short windows and writes the facts out after every
+    // one, so they never have to fit in memory; all the tables end up in a single directory then
+    let mut report_stream = if args.iter().any(|arg| arg == "--stream") {
+        Some(ReportStream::new("out/stream"))
+    } else {
+        None
+    };
+    // `--extend <dir>` skips generating the history, and expands the snapshot saved in the
+    // directory by another month instead, like the second snapshot
+    let extended_dir = args.iter().position(|arg| arg == "--extend").map(|idx| {
+        args.get(idx + 1)
+            .unwrap_or_else(|| usage_error("--extend takes the directory of a snapshot"))
+    });
+    // `--iban` gives the employees' account numbers in the international format, with the country
+    let account_number_format = if args.iter().any(|arg| arg == "--iban") {
+        AccountNumberFormat::Iban
+    } else {
+        AccountNumberFormat::Nrb
+    };
+    // `--names <pack>,...` picks the built-in name packs the people are named from, `polish` and
+    // `english`, and `--name-files <male names> <female names> <surnames>` adds one read from the
+    // files, with a name per line
+    let mut name_packs: Vec<NamePack> = args
+        .iter()
+        .position(|arg| arg == "--names")
+        .map(|idx| {
+            args.get(idx + 1)
+                .unwrap_or_else(|| usage_error("--names takes a list of name packs"))
+                .split(',')
+                .map(|name| {
+                    NamePack::built_in(name).unwrap_or_else(|| {
+                        usage_error(&format!("there's no name pack called {}", name))
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    if let Some(idx) = args.iter().position(|arg| arg == "--name-files") {
+        let paths = args.get(idx + 1..idx + 4).unwrap_or_else(|| {
+            usage_error("--name-files takes the files of male names, female names and surnames")
+        });
+        let pack = NamePack::from_files(&paths[0], &paths[1], &paths[2], SurnameGender::Invariant)
+            .unwrap_or_else(|error| usage_error(&format!("can't read the name files: {}", error)));
+        name_packs.push(pack);
+    }
+    if name_packs.is_empty() {
+        name_packs.push(NamePack::polish());
+    }
+    // `--lateness <exponential|uniform>:<days>` sets how late the reports arriving late are, on
+    // average or at most
+    let lateness = args
+        .iter()
+        .position(|arg| arg == "--lateness")
+        .map(|idx| {
+            args.get(idx + 1)
+                .map(|lateness| lateness.parse())
+                .unwrap_or_else(|| Err("--lateness takes a distribution".to_string()))
+                .unwrap_or_else(|message| usage_error(&message))
+        })
+        .unwrap_or(LatenessDistribution::Exponential {
+            mean: Duration::days(3),
+        });
+
     // configuration data
-    let names = [
-        "Ben", "Bob", "Gus", "Jim", "Joe", "Sam", "Tim", "Tom", "Ada", "Ann", "Deb",
-    ];
-    let surnames = [
-        "Ali", "Ash", "Cho", "Ito", "Kim", "Lis", "Rey", "Sun", "Way", "Xie", "Zhu",
-    ];
+    let employee_salary_ranges = enum_map! {
+        EmployeeRole::Veterinarian => SalaryMinMax { min: 7_000., max: 14_000. },
+        EmployeeRole::Herdsman => SalaryMinMax { min: 3_500., max: 6_000. },
+        EmployeeRole::WarehouseManager => SalaryMinMax { min: 6_000., max: 10_000. },
+        EmployeeRole::Administrator => SalaryMinMax { min: 4_500., max: 9_000. },
+    };
+    let hired_employees_counts = enum_map! {
+        EmployeeRole::Veterinarian => scaled(10, scale),
+        EmployeeRole::Herdsman => scaled(60, scale),
+        EmployeeRole::WarehouseManager => scaled(20, scale),
+        EmployeeRole::Administrator => scaled(10, scale),
+    };
+    // every employee gets a full name of their own, drawn at random, so the packs need plenty more
+    // than there are employees, some of whom are hired later to replace the dismissed ones
+    let employee_count: usize = hired_employees_counts.values().sum();
+    if name_packs
+        .iter()
+        .map(NamePack::full_name_count)
+        .sum::<usize>()
+        < 2 * employee_count
+    {
+        usage_error(&format!(
+            "the name packs are too small to name {} employees each differently, they need at least \
+             {} different full names",
+            employee_count,
+            2 * employee_count
+        ));
+    }
     let pasture_size_ranges = enum_map! {
         PastureKind::Open => PastureAreaMinMax { min: 1_000., max: 10_000. },
         PastureKind::Covered => PastureAreaMinMax { min: 10., max: 900. },
@@ -31,9 +188,12 @@ fn main() {
     };
 
     let report_interval = Duration::days(1);
-    let snapshot2_when = Local::now().naive_local();
-    let snapshot1_when = snapshot2_when - Duration::days(6 * 30 + 3);
-    let initial_when = snapshot1_when - report_interval * 1000;
+    let stream_window = report_interval * 30;
+    // the history ends at the start of the day, so that a seed gives the same timestamps all day
+    let snapshot2_when = Local::now().naive_local().date().and_hms(0, 0, 0);
+    let snapshot1_when = snapshot2_when - Duration::days(scaled(6 * 30 + 3, scale) as i64);
+    let initial_when = snapshot1_when - report_interval * scaled(1000, scale) as i32;
+    let extension = Duration::days(30);
 
     // snapshot setup
     let mut ss = Snapshot::new();
@@ -45,11 +205,12 @@ fn main() {
             639,
             Some(SpeciesAreaRequirements {
                 pasture_kind_to_req_area: enum_map! {
-                    PastureKind::Open => 10.,
-                    PastureKind::Covered => 8.,
+                    PastureKind::Open => 160.,
+                    PastureKind::Covered => 32.,
                     PastureKind::Individual => 2.,
                 },
             }),
+            None,
         ),
         Species::new(
             1,
@@ -58,11 +219,12 @@ fn main() {
             639,
             Some(SpeciesAreaRequirements {
                 pasture_kind_to_req_area: enum_map! {
-                    PastureKind::Open => 10.,
-                    PastureKind::Covered => 8.,
+                    PastureKind::Open => 160.,
+                    PastureKind::Covered => 32.,
                     PastureKind::Individual => 2.,
                 },
             }),
+            None,
         ),
         Species::new(
             2,
@@ -71,11 +233,12 @@ fn main() {
             42,
             Some(SpeciesAreaRequirements {
                 pasture_kind_to_req_area: enum_map! {
-                    PastureKind::Open => 1.,
-                    PastureKind::Covered => 1.,
+                    PastureKind::Open => 16.,
+                    PastureKind::Covered => 4.,
                     PastureKind::Individual => 0.25,
                 },
             }),
+            None,
         ),
         Species::new(
             3,
@@ -84,11 +247,12 @@ fn main() {
             42,
             Some(SpeciesAreaRequirements {
                 pasture_kind_to_req_area: enum_map! {
-                    PastureKind::Open => 1.,
-                    PastureKind::Covered => 1.,
+                    PastureKind::Open => 16.,
+                    PastureKind::Covered => 4.,
                     PastureKind::Individual => 0.25,
                 },
             }),
+            None,
         ),
         Species::new(
             4,
@@ -97,40 +261,440 @@ fn main() {
             304,
             Some(SpeciesAreaRequirements {
                 pasture_kind_to_req_area: enum_map! {
-                    PastureKind::Open => 7.5,
-                    PastureKind::Covered => 6.,
+                    PastureKind::Open => 120.,
+                    PastureKind::Covered => 24.,
                     PastureKind::Individual => 3.,
                 },
             }),
+            None,
+        ),
+        Species::new(
+            5,
+            "Wheat",
+            SpeciesKind::Plant,
+            210,
+            None,
+            Some(CropYield {
+                min_per_area: 0.4,
+                max_per_area: 0.8,
+            }),
+        ),
+        Species::new(
+            6,
+            "Corn",
+            SpeciesKind::Plant,
+            80,
+            None,
+            Some(CropYield {
+                min_per_area: 0.6,
+                max_per_area: 1.1,
+            }),
+        ),
+        Species::new(
+            7,
+            "Soybeans",
+            SpeciesKind::Plant,
+            55,
+            None,
+            Some(CropYield {
+                min_per_area: 0.2,
+                max_per_area: 0.4,
+            }),
         ),
-        Species::new(5, "Wheat", SpeciesKind::Plant, 210, None),
-        Species::new(6, "Corn", SpeciesKind::Plant, 80, None),
-        Species::new(7, "Soybeans", SpeciesKind::Plant, 55, None),
     ];
-    let mut species_for_herds = vec![0, 1, 2, 3, 4];
+    ss.products = vec![
+        Product::new(
+            0,
+            "Compound Feed",
+            ProductKind::Feed,
+            "kg",
+            0.0016,
+            1.6,
+            None,
+        ),
+        Product::new(
+            1,
+            "Beef Cattle Feed",
+            ProductKind::Feed,
+            "kg",
+            0.0016,
+            1.8,
+            Some(0),
+        ),
+        Product::new(
+            2,
+            "Dairy Cattle Feed",
+            ProductKind::Feed,
+            "kg",
+            0.0016,
+            1.9,
+            Some(1),
+        ),
+        Product::new(
+            3,
+            "Layer Feed",
+            ProductKind::Feed,
+            "kg",
+            0.0015,
+            2.,
+            Some(2),
+        ),
+        Product::new(
+            4,
+            "Turkey Feed",
+            ProductKind::Feed,
+            "kg",
+            0.0015,
+            2.2,
+            Some(3),
+        ),
+        Product::new(
+            5,
+            "Sheep Feed",
+            ProductKind::Feed,
+            "kg",
+            0.0017,
+            1.7,
+            Some(4),
+        ),
+        Product::new(
+            6,
+            "Wheat Grain",
+            ProductKind::Crop,
+            "kg",
+            0.0013,
+            0.9,
+            Some(5),
+        ),
+        Product::new(
+            7,
+            "Corn Grain",
+            ProductKind::Crop,
+            "kg",
+            0.0014,
+            0.8,
+            Some(6),
+        ),
+        Product::new(8, "Soybeans", ProductKind::Crop, "kg", 0.0013, 1.9, Some(7)),
+        Product::new(
+            9,
+            "Milk",
+            ProductKind::AnimalProduct,
+            "l",
+            0.001,
+            2.1,
+            Some(1),
+        ),
+        Product::new(
+            10,
+            "Eggs",
+            ProductKind::AnimalProduct,
+            "pcs",
+            6e-05,
+            0.7,
+            Some(2),
+        ),
+        Product::new(
+            11,
+            "Wool",
+            ProductKind::AnimalProduct,
+            "kg",
+            0.01,
+            5.,
+            Some(4),
+        ),
+        Product::new(12, "Beef", ProductKind::Meat, "kg", 0.0011, 28., None),
+        Product::new(
+            13,
+            "Chicken Meat",
+            ProductKind::Meat,
+            "kg",
+            0.0011,
+            12.,
+            Some(2),
+        ),
+        Product::new(
+            14,
+            "Turkey Meat",
+            ProductKind::Meat,
+            "kg",
+            0.0011,
+            18.,
+            Some(3),
+        ),
+        Product::new(15, "Lamb", ProductKind::Meat, "kg", 0.0011, 35., Some(4)),
+        Product::new(
+            16,
+            "Veterinary Antibiotic",
+            ProductKind::Medicine,
+            "dose",
+            0.00002,
+            4.5,
+            None,
+        ),
+        Product::new(
+            17,
+            "Poultry Antibiotic",
+            ProductKind::Medicine,
+            "dose",
+            0.000005,
+            0.3,
+            Some(2),
+        ),
+    ];
+    let production_profiles = vec![
+        // milk yield peaks in late spring, when the pastures are richest
+        ProductionProfile {
+            species_id: 1,
+            product_id: 9,
+            yield_per_animal_per_day: 25.,
+            report_interval_days: 1,
+            seasonal_amplitude: 0.15,
+            peak_day_of_year: 140,
+            customer_kinds: vec![CustomerKind::Dairy, CustomerKind::Wholesaler],
+        },
+        // hens lay the most during the longest days
+        ProductionProfile {
+            species_id: 2,
+            product_id: 10,
+            yield_per_animal_per_day: 0.8,
+            report_interval_days: 1,
+            seasonal_amplitude: 0.25,
+            peak_day_of_year: 172,
+            customer_kinds: vec![
+                CustomerKind::Retailer,
+                CustomerKind::Restaurant,
+                CustomerKind::Wholesaler,
+            ],
+        },
+        // sheep are sheared twice a year, the winter fleece is the heavier one
+        ProductionProfile {
+            species_id: 4,
+            product_id: 11,
+            yield_per_animal_per_day: 4.5 / 365.,
+            report_interval_days: 182,
+            seasonal_amplitude: 0.1,
+            peak_day_of_year: 100,
+            customer_kinds: vec![CustomerKind::Wholesaler],
+        },
+    ];
+    let butchery_profiles = vec![
+        ButcheryProfile {
+            species_id: 0,
+            product_id: 12,
+            dressing_pct: 0.58,
+            customer_kinds: vec![
+                CustomerKind::Butcher,
+                CustomerKind::Restaurant,
+                CustomerKind::Retailer,
+            ],
+        },
+        ButcheryProfile {
+            species_id: 1,
+            product_id: 12,
+            dressing_pct: 0.55,
+            customer_kinds: vec![
+                CustomerKind::Butcher,
+                CustomerKind::Restaurant,
+                CustomerKind::Retailer,
+            ],
+        },
+        ButcheryProfile {
+            species_id: 2,
+            product_id: 13,
+            dressing_pct: 0.72,
+            customer_kinds: vec![
+                CustomerKind::Butcher,
+                CustomerKind::Restaurant,
+                CustomerKind::Retailer,
+            ],
+        },
+        ButcheryProfile {
+            species_id: 3,
+            product_id: 14,
+            dressing_pct: 0.78,
+            customer_kinds: vec![
+                CustomerKind::Butcher,
+                CustomerKind::Restaurant,
+                CustomerKind::Retailer,
+            ],
+        },
+        ButcheryProfile {
+            species_id: 4,
+            product_id: 15,
+            dressing_pct: 0.48,
+            customer_kinds: vec![
+                CustomerKind::Butcher,
+                CustomerKind::Restaurant,
+                CustomerKind::Retailer,
+            ],
+        },
+    ];
+    let disposal_profiles = vec![
+        DisposalProfile {
+            species_id: 0,
+            early_disposal_pcts: vec![
+                (DisposalPurpose::Health, 0.03),
+                (DisposalPurpose::SaleAlive, 0.03),
+                (DisposalPurpose::Transfer, 0.01),
+                (DisposalPurpose::NaturalDeath, 0.01),
+                (DisposalPurpose::Accident, 0.005),
+                (DisposalPurpose::PredatorLoss, 0.002),
+            ],
+            weight_at_birth: 35.,
+            weight_at_lifespan: 600.,
+            price_per_kg: 9.,
+        },
+        DisposalProfile {
+            species_id: 1,
+            early_disposal_pcts: vec![
+                (DisposalPurpose::Health, 0.04),
+                (DisposalPurpose::SaleAlive, 0.03),
+                (DisposalPurpose::Transfer, 0.02),
+                (DisposalPurpose::NaturalDeath, 0.01),
+                (DisposalPurpose::Accident, 0.005),
+                (DisposalPurpose::PredatorLoss, 0.001),
+            ],
+            weight_at_birth: 40.,
+            weight_at_lifespan: 650.,
+            price_per_kg: 7.5,
+        },
+        // poultry is the easiest prey and dies off more often on its own
+        DisposalProfile {
+            species_id: 2,
+            early_disposal_pcts: vec![
+                (DisposalPurpose::Health, 0.03),
+                (DisposalPurpose::SaleAlive, 0.01),
+                (DisposalPurpose::NaturalDeath, 0.04),
+                (DisposalPurpose::Accident, 0.005),
+                (DisposalPurpose::PredatorLoss, 0.02),
+            ],
+            weight_at_birth: 0.04,
+            weight_at_lifespan: 2.2,
+            price_per_kg: 5.,
+        },
+        DisposalProfile {
+            species_id: 3,
+            early_disposal_pcts: vec![
+                (DisposalPurpose::Health, 0.03),
+                (DisposalPurpose::SaleAlive, 0.02),
+                (DisposalPurpose::NaturalDeath, 0.03),
+                (DisposalPurpose::Accident, 0.005),
+                (DisposalPurpose::PredatorLoss, 0.015),
+            ],
+            weight_at_birth: 0.06,
+            weight_at_lifespan: 15.,
+            price_per_kg: 7.,
+        },
+        DisposalProfile {
+            species_id: 4,
+            early_disposal_pcts: vec![
+                (DisposalPurpose::Health, 0.03),
+                (DisposalPurpose::SaleAlive, 0.03),
+                (DisposalPurpose::Transfer, 0.01),
+                (DisposalPurpose::NaturalDeath, 0.01),
+                (DisposalPurpose::Accident, 0.005),
+                (DisposalPurpose::PredatorLoss, 0.02),
+            ],
+            weight_at_birth: 4.,
+            weight_at_lifespan: 80.,
+            price_per_kg: 10.,
+        },
+    ];
+    let mut config = FarmConfig {
+        report_interval,
+        pasture_size_ranges,
+        species_for_herds: vec![0, 1, 2, 3, 4],
+        max_herds_per_pasture: 3,
+        stocking_pct: 0.75,
+        field_area_min: 10_000.,
+        field_area_max: 100_000.,
+        species_for_fields: vec![5, 6, 7],
+        hiring: HiringConfig {
+            name_packs,
+            unique_identities: true,
+            salary_ranges: employee_salary_ranges,
+            invalid_pesel_pct: 0.01,
+            account_number_format,
+        },
+        overtime_pct: 0.1,
+        payroll_bonus: BonusConfig {
+            chance: 0.1,
+            min_pct: 0.05,
+            max_pct: 0.2,
+        },
+        purchase: PurchaseConfig {
+            delivery_delay_min_days: 1,
+            delivery_delay_max_days: 14,
+            yearly_price_drift: 0.06,
+            max_price_variation: 0.05,
+            price_reference_date: initial_when.date(),
+        },
+        feed_delivery_quantity: 20_000.,
+        medicine_delivery_quantity: 200_000.,
+        disposal_profiles,
+        illness: IllnessConfig {
+            ill_max_pct: 0.1,
+            severly_ill_max_pct: 0.07,
+            terminal_max_pct: 0.02,
+        },
+        production_profiles,
+        butchery_profiles,
+        crop_customer_kinds: vec![CustomerKind::Wholesaler],
+        late_arrivals: Some(LateArrivalConfig {
+            late_pct: 0.05,
+            lateness,
+            correction_pct: 0.01,
+        }),
+    };
+
+    // time of day dimension, for joining the timestamps at minute precision
+    if dim_time {
+        ss.dim_times = dim_time_vec();
+    }
 
     // first snapshot
-    ss.expand(
-        initial_when,
-        snapshot1_when,
-        report_interval,
-        1000,
-        &pasture_size_ranges,
-        species_for_herds.as_slice(),
-        100,
-        &names,
-        &surnames,
-        3000.0,
-        12000.0,
-        16,
-        0.1,
-        9000,
-        12000,
-        0.1,
-        0.07,
-        0.02,
-    );
-    ss.save_to_dir("out/snapshot1");
+    let windows = match (extended_dir, &report_stream) {
+        (Some(_), _) => vec![],
+        (None, Some(_)) => stream_windows(initial_when, snapshot1_when, stream_window),
+        (None, None) => vec![(initial_when, snapshot1_when)],
+    };
+    for (idx, &(from_when, to_when)) in windows.iter().enumerate() {
+        // new entities come in the first window, rates given per expansion are split between them
+        let first_only = |count: usize| if idx == 0 { count } else { 0 };
+        let share = to_when.signed_duration_since(from_when).num_seconds() as f32
+            / snapshot1_when
+                .signed_duration_since(initial_when)
+                .num_seconds() as f32;
+        ss.expand(
+            from_when,
+            to_when,
+            &config,
+            &Growth {
+                new_pasture_count: first_only(scaled(1000, scale)),
+                new_field_count: first_only(40),
+                hired_employee_counts: if idx == 0 {
+                    hired_employees_counts
+                } else {
+                    enum_map! { _ => 0 }
+                },
+                dismiss_pct: 0.05 * share,
+                new_warehouse_count: first_only(scaled(16, scale)),
+                new_supplier_count: first_only(10),
+                new_customer_count: first_only(50),
+            },
+        );
+        if let Some(report_stream) = report_stream.as_mut() {
+            ss.stream_tables(report_stream);
+        }
+    }
+    if report_stream.is_none() && extended_dir.is_none() {
+        ss.save_to_dir("out/snapshot1");
+        ss.save_aggregates_to_dir("out/snapshot1/aggregates");
+        if star_schema {
+            ss.save_star_schema_to_dir("out/snapshot1/star_schema");
+        }
+    }
 
     // expand the species and make a second snapshot
     ss.species.push(Species::new(
@@ -140,34 +704,114 @@ fn main() {
         167,
         Some(SpeciesAreaRequirements {
             pasture_kind_to_req_area: enum_map! {
-                PastureKind::Open => 9.,
-                PastureKind::Covered => 7.,
+                PastureKind::Open => 144.,
+                PastureKind::Covered => 28.,
                 PastureKind::Individual => 4.,
             },
         }),
+        None,
     ));
-    species_for_herds.push(ss.species.len() - 1);
-    ss.expand(
-        snapshot1_when,
-        snapshot2_when,
-        report_interval,
-        100,
-        &pasture_size_ranges,
-        &species_for_herds,
-        0,
-        &names,
-        &surnames,
-        3000.0,
-        12000.0,
-        0,
-        0.2,
-        9000,
-        12000,
-        0.1,
-        0.07,
-        0.02,
-    );
+    config.species_for_herds.push(ss.species.len() - 1);
+    ss.products.push(Product::new(
+        ss.products.len(),
+        "Pork",
+        ProductKind::Meat,
+        "kg",
+        0.0011,
+        20.,
+        Some(ss.species.len() - 1),
+    ));
+    config.butchery_profiles.push(ButcheryProfile {
+        species_id: ss.species.len() - 1,
+        product_id: ss.products.len() - 1,
+        dressing_pct: 0.76,
+        customer_kinds: vec![
+            CustomerKind::Butcher,
+            CustomerKind::Restaurant,
+            CustomerKind::Retailer,
+        ],
+    });
+    config.disposal_profiles.push(DisposalProfile {
+        species_id: ss.species.len() - 1,
+        early_disposal_pcts: vec![
+            (DisposalPurpose::Health, 0.04),
+            (DisposalPurpose::SaleAlive, 0.03),
+            (DisposalPurpose::Transfer, 0.01),
+            (DisposalPurpose::NaturalDeath, 0.01),
+            (DisposalPurpose::Accident, 0.005),
+        ],
+        weight_at_birth: 1.5,
+        weight_at_lifespan: 115.,
+        price_per_kg: 6.,
+    });
+    let (snapshot2_from, snapshot2_to) = match extended_dir {
+        Some(dir) => {
+            ss = Snapshot::load_from_dir(dir, ss.species, ss.products).unwrap_or_else(|error| {
+                usage_error(&format!("can't load the snapshot in {}: {}", dir, error))
+            });
+            if !dim_time {
+                ss.dim_times.clear();
+            }
+            // the extension carries on from where the snapshot stopped
+            let from_when = ss
+                .taken_at()
+                .unwrap_or_else(|| usage_error(&format!("the snapshot in {} is empty", dir)));
+            (from_when, from_when + extension)
+        }
+        None => (snapshot1_when, snapshot2_when),
+    };
+    let windows = match report_stream {
+        Some(_) => stream_windows(snapshot2_from, snapshot2_to, stream_window),
+        None => vec![(snapshot2_from, snapshot2_to)],
+    };
+    for (idx, &(from_when, to_when)) in windows.iter().enumerate() {
+        let first_only = |count: usize| if idx == 0 { count } else { 0 };
+        let share = to_when.signed_duration_since(from_when).num_seconds() as f32
+            / snapshot2_to
+                .signed_duration_since(snapshot2_from)
+                .num_seconds() as f32;
+        ss.expand(
+            from_when,
+            to_when,
+            &config,
+            &Growth {
+                // the pastures of an extended snapshot are all there already
+                new_pasture_count: match extended_dir {
+                    Some(_) => 0,
+                    None => first_only(scaled(100, scale)),
+                },
+                new_field_count: first_only(5),
+                hired_employee_counts: enum_map! { _ => 0 },
+                dismiss_pct: 0.02 * share,
+                new_warehouse_count: 0,
+                new_supplier_count: first_only(2),
+                new_customer_count: first_only(5),
+            },
+        );
+        if let Some(report_stream) = report_stream.as_mut() {
+            ss.stream_tables(report_stream);
+        }
+    }
     // SCD in second snapshot
     randomly_enlarge_warehouses(&mut ss.warehouses, 40000., 90000.);
-    ss.save_to_dir("out/snapshot2");
+    if let Some(report_stream) = report_stream.as_mut() {
+        ss.finish_stream(report_stream);
+        ss.save_held_tables_to_dir("out/stream");
+        return;
+    }
+    let snapshot2_dir = match extended_dir {
+        Some(_) => "out/extended",
+        None => "out/snapshot2",
+    };
+    ss.save_to_dir(snapshot2_dir);
+    ss.save_aggregates_to_dir(&format!("{}/aggregates", snapshot2_dir));
+    if star_schema {
+        ss.save_star_schema_to_dir(&format!("{}/star_schema", snapshot2_dir));
+    }
+}
+
+// Exits like a command line parser would on arguments it can't make sense of
+fn usage_error(message: &str) -> ! {
+    eprintln!("error: {}", message);
+    std::process::exit(2);
 }
@@ -0,0 +1,150 @@
+use std::{
+    cell::{Cell, RefCell},
+    env, thread,
+};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+const THREADS_VAR: &str = "GENERATOR_THREADS";
+
+thread_local! {
+    // Where the generators' random streams are seeded from, in the order the generators run
+    static SEED_RNG: RefCell<StdRng> = RefCell::new(StdRng::from_entropy());
+    // How many threads the generators run on, when it's not up to the environment
+    static THREAD_COUNT: Cell<Option<usize>> = const { Cell::new(None) };
+}
+
+// Makes everything generated on this thread from now on follow from the seed
+pub fn seed_generators(seed: u64) {
+    SEED_RNG.with(|rng| *rng.borrow_mut() = StdRng::seed_from_u64(seed));
+}
+
+// A random stream of its own for every time a generator runs
+pub fn generator_rng() -> StdRng {
+    SEED_RNG.with(|rng| StdRng::seed_from_u64(rng.borrow_mut().gen()))
+}
+
+// Every entity draws from its own random stream, so what's generated for it doesn't depend on which
+// thread does it, or how many there are
+pub fn entity_rng(seed: u64, entity_id: usize) -> StdRng {
+    StdRng::seed_from_u64(seed ^ (entity_id as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15))
+}
+
+// Makes the generators run on this thread spread their work over the given number of threads,
+// whatever the environment says
+#[cfg(test)]
+pub fn use_threads(count: usize) {
+    THREAD_COUNT.with(|thread_count| thread_count.set(Some(count)));
+}
+
+// All the available cores, unless limited with the GENERATOR_THREADS environment variable
+pub fn thread_count() -> usize {
+    THREAD_COUNT
+        .with(Cell::get)
+        .or_else(|| {
+            env::var(THREADS_VAR)
+                .ok()
+                .and_then(|count| count.parse().ok())
+        })
+        .or_else(|| {
+            thread::available_parallelism()
+                .ok()
+                .map(|count| count.get())
+        })
+        .unwrap_or(1)
+        .max(1)
+}
+
+// Maps the items in chunks spread over the threads, keeping their order
+pub fn par_map<T, U, F>(items: &[T], map: F) -> Vec<U>
+where
+    T: Sync,
+    U: Send,
+    F: Fn(&T) -> U + Sync,
+{
+    let thread_count = thread_count();
+    let chunk_size = items.len().div_ceil(thread_count).max(1);
+    let map = &map;
+    thread::scope(|scope| {
+        let handles: Vec<_> = items
+            .chunks(chunk_size)
+            .map(|chunk| scope.spawn(move || chunk.iter().map(map).collect::<Vec<U>>()))
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, NaiveDate};
+    use enum_map::enum_map;
+    use serde::Serialize;
+
+    use super::*;
+    use crate::{
+        feeding_report::expand_feeding_report_vec,
+        herd::Herd,
+        livestock::expand_livestock,
+        pasture::{Pasture, PastureKind},
+        species::{Species, SpeciesAreaRequirements, SpeciesKind},
+        table::Table,
+        weather::expand_weather_vec,
+    };
+
+    fn to_csv<T: Serialize>(rows: &[T]) -> String {
+        let mut writer = csv::Writer::from_writer(vec![]);
+        for row in rows {
+            writer.serialize(row).unwrap();
+        }
+        String::from_utf8(writer.into_inner().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn same_data_whatever_the_thread_count() {
+        let from_when = NaiveDate::from_ymd(2024, 1, 1).and_hms(0, 0, 0);
+        let to_when = from_when + Duration::days(90);
+        let species = [Species::new(
+            0,
+            "Cattle",
+            SpeciesKind::Animal,
+            60,
+            Some(SpeciesAreaRequirements {
+                pasture_kind_to_req_area: enum_map! { _ => 10. },
+            }),
+            None,
+        )];
+        let pastures: Vec<Pasture> = (0..40)
+            .map(|id| Pasture::new(id, 100. + id as f32 * 10., PastureKind::Open))
+            .collect();
+        let herds: Vec<Herd> = pastures
+            .iter()
+            .map(|pasture| Herd::new(pasture.id, pasture, &species[0], pasture.area))
+            .collect();
+
+        let generate = |threads: usize| {
+            use_threads(threads);
+            seed_generators(42);
+            let mut weather = vec![];
+            expand_weather_vec(&mut weather, from_when.date(), to_when.date());
+            let mut feeding_reports = Table::new();
+            expand_feeding_report_vec(
+                &mut feeding_reports,
+                &[],
+                &pastures,
+                &weather,
+                from_when,
+                to_when,
+                Duration::days(1),
+            );
+            let mut livestock = Table::new();
+            expand_livestock(&mut livestock, &herds, &species, 0.75, from_when, to_when);
+            (to_csv(&feeding_reports), to_csv(&livestock))
+        };
+        let single_threaded = generate(1);
+        let multi_threaded = generate(4);
+        assert_eq!(single_threaded, multi_threaded);
+    }
+}
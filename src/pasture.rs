@@ -2,21 +2,25 @@ use core::panic;
 
 use enum_map::{Enum, EnumMap};
 use rand::{self, Rng};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Copy, Enum, Serialize)]
+use crate::parallel::generator_rng;
+
+#[derive(Clone, Copy, Enum, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum PastureKind {
     Open,
     Covered,
     Individual,
 }
 
+const FEEDER_CAPACITY_PER_AREA: f32 = 0.05; // in kilograms of feed per square meter
+
 pub struct PastureAreaMinMax {
     pub min: f32,
     pub max: f32,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize)]
 pub struct Pasture {
     pub id: usize,
     pub area: f32,
@@ -27,6 +31,10 @@ impl Pasture {
     pub const fn new(id: usize, area: f32, kind: PastureKind) -> Self {
         Pasture { id, area, kind }
     }
+
+    pub fn feeder_capacity(&self) -> f32 {
+        self.area * FEEDER_CAPACITY_PER_AREA
+    }
 }
 
 pub fn expand_pasture_vec(
@@ -34,7 +42,7 @@ pub fn expand_pasture_vec(
     count: usize,
     size_ranges: &EnumMap<PastureKind, PastureAreaMinMax>,
 ) {
-    let mut rng = rand::thread_rng();
+    let mut rng = generator_rng();
 
     for _ in 0..count {
         let kind = match rng.gen_range(0..3) {
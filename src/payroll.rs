@@ -0,0 +1,310 @@
+use std::collections::HashMap;
+
+use chrono::{Datelike, Duration, NaiveDate};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{employee::Employee, parallel::generator_rng, shift::Shift, table::Table};
+
+// Employee's share of the social insurance (pension, disability and sickness)
+const SOCIAL_CONTRIBUTIONS_PCT: f32 = 0.0976 + 0.015 + 0.0245;
+// Employer's share of the social insurance, accident insurance, Labour Fund and FGŚP
+const EMPLOYER_CONTRIBUTIONS_PCT: f32 = 0.0976 + 0.065 + 0.0167 + 0.0245 + 0.001;
+const HEALTH_CONTRIBUTION_PCT: f32 = 0.09;
+const TAX_DEDUCTIBLE_COSTS: f32 = 250.;
+const TAX_REDUCING_AMOUNT: f32 = 300.;
+const TAX_THRESHOLD: f32 = 120_000.;
+const LOWER_TAX_PCT: f32 = 0.12;
+const HIGHER_TAX_PCT: f32 = 0.32;
+const OVERTIME_PAY_MULTIPLIER: f32 = 1.5;
+const MONTHLY_WORK_HOURS: f32 = 168.;
+
+pub struct BonusConfig {
+    pub chance: f32,
+    pub min_pct: f32,
+    pub max_pct: f32,
+}
+
+// What an employee earned in a month, before the contributions and the tax
+pub struct Pay {
+    pub base: f32,
+    pub overtime_hours: f32,
+    pub overtime: f32,
+    pub bonus: f32,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Payroll {
+    id: usize,
+    employee_id: usize,
+    pub month: NaiveDate,
+    base_pay: f32,
+    overtime_hours: f32,
+    overtime_pay: f32,
+    bonus: f32,
+    gross: f32,
+    social_contributions: f32,
+    health_contribution: f32,
+    income_tax: f32,
+    net: f32,
+    employer_contributions: f32,
+}
+
+impl Payroll {
+    pub fn new(
+        id: usize,
+        employee: &Employee,
+        month: NaiveDate,
+        pay: Pay,
+        yearly_tax_base_so_far: f32,
+    ) -> Self {
+        let gross = round_to_grosz(pay.base + pay.overtime + pay.bonus);
+        let social_contributions = round_to_grosz(gross * SOCIAL_CONTRIBUTIONS_PCT);
+        let health_contribution =
+            round_to_grosz((gross - social_contributions) * HEALTH_CONTRIBUTION_PCT);
+        let income_tax = income_tax(
+            taxable_income(gross, social_contributions),
+            yearly_tax_base_so_far,
+        );
+        Payroll {
+            id,
+            employee_id: employee.id,
+            month,
+            base_pay: round_to_grosz(pay.base),
+            overtime_hours: pay.overtime_hours,
+            overtime_pay: round_to_grosz(pay.overtime),
+            bonus: round_to_grosz(pay.bonus),
+            gross,
+            social_contributions,
+            health_contribution,
+            income_tax,
+            net: round_to_grosz(gross - social_contributions - health_contribution - income_tax),
+            employer_contributions: round_to_grosz(gross * EMPLOYER_CONTRIBUTIONS_PCT),
+        }
+    }
+
+    pub fn tax_base(&self) -> f32 {
+        taxable_income(self.gross, self.social_contributions)
+    }
+}
+
+// Payroll is generated for the months which end within the given days
+pub fn expand_payroll_vec(
+    payrolls: &mut Table<Payroll>,
+    employees: &[Employee],
+    shifts: &[Shift],
+    first_day: NaiveDate,
+    last_day: NaiveDate,
+    bonus: &BonusConfig,
+) {
+    let mut rng = generator_rng();
+
+    let mut overtime_hours: HashMap<(usize, NaiveDate), f32> = HashMap::new();
+    for shift in shifts {
+        let overtime = shift.overtime();
+        if overtime > Duration::zero() {
+            *overtime_hours
+                .entry((shift.employee_id, first_day_of_month(shift.start.date())))
+                .or_insert(0.) += overtime.num_minutes() as f32 / 60.;
+        }
+    }
+    // the higher tax bracket depends on everything earned since the start of the year
+    let mut yearly_tax_bases: HashMap<(usize, i32), f32> = HashMap::new();
+    for payroll in payrolls.iter() {
+        *yearly_tax_bases
+            .entry((payroll.employee_id, payroll.month.year()))
+            .or_insert(0.) += payroll.tax_base();
+    }
+
+    let mut month = first_day_of_month(first_day);
+    loop {
+        let next_month = first_day_of_next_month(month);
+        let month_last_day = next_month.pred();
+        if month_last_day >= last_day {
+            break;
+        }
+        if month_last_day >= first_day {
+            let days_in_month = next_month.signed_duration_since(month).num_days() as f32;
+            for employee in employees {
+                let employed_days = (0..days_in_month as i64)
+                    .filter(|&day| employee.is_employed_on(month + Duration::days(day)))
+                    .count() as f32;
+                if employed_days == 0. {
+                    continue;
+                }
+                let base_pay = employee.salary * employed_days / days_in_month;
+                let overtime_hours = *overtime_hours.get(&(employee.id, month)).unwrap_or(&0.);
+                let overtime_pay =
+                    overtime_hours * employee.salary / MONTHLY_WORK_HOURS * OVERTIME_PAY_MULTIPLIER;
+                let bonus = if rng.gen_bool(bonus.chance as f64) {
+                    base_pay * rng.gen_range(bonus.min_pct..=bonus.max_pct)
+                } else {
+                    0.
+                };
+                let yearly_tax_base = yearly_tax_bases
+                    .entry((employee.id, month.year()))
+                    .or_insert(0.);
+                let payroll = Payroll::new(
+                    payrolls.next_id(),
+                    employee,
+                    month,
+                    Pay {
+                        base: base_pay,
+                        overtime_hours,
+                        overtime: overtime_pay,
+                        bonus,
+                    },
+                    *yearly_tax_base,
+                );
+                *yearly_tax_base += payroll.tax_base();
+                payrolls.push(payroll);
+            }
+        }
+        month = next_month;
+    }
+}
+
+fn taxable_income(gross: f32, social_contributions: f32) -> f32 {
+    (gross - social_contributions - TAX_DEDUCTIBLE_COSTS)
+        .max(0.)
+        .round()
+}
+
+fn income_tax(tax_base: f32, yearly_tax_base_so_far: f32) -> f32 {
+    let lower_bracket_base = (TAX_THRESHOLD - yearly_tax_base_so_far).clamp(0., tax_base);
+    let higher_bracket_base = tax_base - lower_bracket_base;
+    let tax = lower_bracket_base * LOWER_TAX_PCT + higher_bracket_base * HIGHER_TAX_PCT
+        - TAX_REDUCING_AMOUNT;
+    tax.max(0.).round()
+}
+
+fn round_to_grosz(amount: f32) -> f32 {
+    (amount * 100.).round() / 100.
+}
+
+pub fn first_day_of_month(day: NaiveDate) -> NaiveDate {
+    NaiveDate::from_ymd(day.year(), day.month(), 1)
+}
+
+fn first_day_of_next_month(month: NaiveDate) -> NaiveDate {
+    if month.month() == 12 {
+        NaiveDate::from_ymd(month.year() + 1, 1, 1)
+    } else {
+        NaiveDate::from_ymd(month.year(), month.month() + 1, 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::employee::{EmployeeRole, Identity, Sex};
+
+    fn employee() -> Employee {
+        let identity = Identity {
+            name: "Jan".to_string(),
+            surname: "Kowalski".to_string(),
+            sex: Sex::Male,
+            birth_date: NaiveDate::from_ymd(1980, 1, 1),
+            pesel: "80010100011".to_string(),
+        };
+        Employee::new(
+            0,
+            identity,
+            String::new(),
+            6_000.,
+            EmployeeRole::Herdsman,
+            NaiveDate::from_ymd(2024, 1, 1),
+        )
+    }
+
+    fn pay(base: f32) -> Pay {
+        Pay {
+            base,
+            overtime_hours: 0.,
+            overtime: 0.,
+            bonus: 0.,
+        }
+    }
+
+    #[test]
+    fn payroll_in_the_lower_bracket() {
+        let payroll = Payroll::new(
+            0,
+            &employee(),
+            NaiveDate::from_ymd(2024, 3, 1),
+            pay(6_000.),
+            0.,
+        );
+        // 13.71% of 6000
+        assert_eq!(payroll.social_contributions, 822.6);
+        // 9% of 6000 - 822.60
+        assert_eq!(payroll.health_contribution, 465.97);
+        // 12% of 6000 - 822.60 - 250 rounded to 4927, less 300
+        assert_eq!(payroll.income_tax, 291.);
+        assert_eq!(payroll.net, 4_420.43);
+        // 20.48% of 6000
+        assert_eq!(payroll.employer_contributions, 1_228.8);
+        assert_eq!(payroll.tax_base(), 4_927.);
+    }
+
+    #[test]
+    fn payroll_crossing_the_threshold() {
+        // 2000 of the 4927 left in the lower bracket at 12%, the rest at 32%, less 300
+        let payroll = Payroll::new(
+            0,
+            &employee(),
+            NaiveDate::from_ymd(2024, 11, 1),
+            pay(6_000.),
+            118_000.,
+        );
+        assert_eq!(payroll.income_tax, 877.);
+        // all of it at 32%, less 300
+        let payroll = Payroll::new(
+            0,
+            &employee(),
+            NaiveDate::from_ymd(2024, 12, 1),
+            pay(6_000.),
+            130_000.,
+        );
+        assert_eq!(payroll.income_tax, 1_277.);
+    }
+
+    #[test]
+    fn overtime_and_bonus_are_part_of_the_gross() {
+        let pay = Pay {
+            base: 3_000.,
+            overtime_hours: 10.,
+            overtime: 10. * 3_000. / MONTHLY_WORK_HOURS * OVERTIME_PAY_MULTIPLIER,
+            bonus: 150.,
+        };
+        let payroll = Payroll::new(0, &employee(), NaiveDate::from_ymd(2024, 3, 1), pay, 0.);
+        assert_eq!(payroll.overtime_pay, 267.86);
+        assert_eq!(payroll.gross, 3_417.86);
+    }
+
+    #[test]
+    fn low_income_isnt_taxed() {
+        // 12% of 1000 - 137.10 - 250 is less than the tax reducing amount
+        let payroll = Payroll::new(
+            0,
+            &employee(),
+            NaiveDate::from_ymd(2024, 3, 1),
+            pay(1_000.),
+            0.,
+        );
+        assert_eq!(payroll.income_tax, 0.);
+        assert_eq!(payroll.net, 1_000. - 137.1 - 77.66);
+    }
+
+    #[test]
+    fn month_boundaries() {
+        assert_eq!(
+            first_day_of_month(NaiveDate::from_ymd(2024, 2, 29)),
+            NaiveDate::from_ymd(2024, 2, 1)
+        );
+        assert_eq!(
+            first_day_of_next_month(NaiveDate::from_ymd(2024, 12, 1)),
+            NaiveDate::from_ymd(2025, 1, 1)
+        );
+    }
+}
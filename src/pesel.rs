@@ -0,0 +1,119 @@
+use chrono::{Datelike, NaiveDate};
+use rand::Rng;
+
+use crate::employee::Sex;
+
+const CHECKSUM_WEIGHTS: [u32; 10] = [1, 3, 7, 9, 1, 3, 7, 9, 1, 3];
+
+pub fn random_pesel<R: Rng>(rng: &mut R, birth_date: NaiveDate, sex: Sex) -> String {
+    // the century is encoded in the month by adding a fixed offset
+    let century_month_offset = match birth_date.year() {
+        1800..=1899 => 80,
+        1900..=1999 => 0,
+        2000..=2099 => 20,
+        2100..=2199 => 40,
+        2200..=2299 => 60,
+        _ => panic!("PESEL can't encode a birth date in {}", birth_date.year()),
+    };
+    let serial = rng.gen_range(0..=999);
+    let sex_digit = match sex {
+        Sex::Male => rng.gen_range(0..5) * 2 + 1,
+        Sex::Female => rng.gen_range(0..5) * 2,
+    };
+    let digits = format!(
+        "{:02}{:02}{:02}{:03}{}",
+        birth_date.year() % 100,
+        birth_date.month() + century_month_offset,
+        birth_date.day(),
+        serial,
+        sex_digit
+    );
+    let check_digit = pesel_check_digit(&digits);
+    format!("{}{}", digits, check_digit)
+}
+
+// Breaks either the checksum or the encoded birth date of a valid PESEL
+pub fn corrupt_pesel<R: Rng>(rng: &mut R, pesel: &str) -> String {
+    if rng.gen_bool(0.5) {
+        let check_digit = pesel[10..].parse::<u32>().unwrap();
+        let wrong_check_digit = (check_digit + rng.gen_range(1..10)) % 10;
+        format!("{}{}", &pesel[..10], wrong_check_digit)
+    } else {
+        // day 32 doesn't exist in any month, the checksum is kept valid to only fail the date check
+        let digits = format!("{}32{}", &pesel[..4], &pesel[6..10]);
+        let check_digit = pesel_check_digit(&digits);
+        format!("{}{}", digits, check_digit)
+    }
+}
+
+fn pesel_check_digit(digits: &str) -> u32 {
+    let sum: u32 = digits
+        .chars()
+        .zip(CHECKSUM_WEIGHTS.iter())
+        .map(|(digit, weight)| digit.to_digit(10).unwrap() * weight)
+        .sum();
+    (10 - sum % 10) % 10
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    // The birth date a PESEL encodes, if its checksum and date are valid
+    fn decode_birth_date(pesel: &str) -> Option<NaiveDate> {
+        if pesel_check_digit(&pesel[..10]) != pesel[10..].parse::<u32>().unwrap() {
+            return None;
+        }
+        let year = pesel[..2].parse::<i32>().unwrap();
+        let month = pesel[2..4].parse::<u32>().unwrap();
+        let day = pesel[4..6].parse::<u32>().unwrap();
+        let century = [1900, 2000, 2100, 2200, 1800][(month / 20) as usize];
+        NaiveDate::from_ymd_opt(century + year, month % 20, day)
+    }
+
+    #[test]
+    fn check_digit_of_known_pesels() {
+        assert_eq!(pesel_check_digit("4405140135"), 9);
+        assert_eq!(pesel_check_digit("0207080362"), 8);
+        assert_eq!(pesel_check_digit("9207131476"), 4);
+    }
+
+    #[test]
+    fn random_pesel_encodes_birth_date_and_sex() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for (birth_date, sex) in [
+            (NaiveDate::from_ymd(1944, 5, 14), Sex::Male),
+            (NaiveDate::from_ymd(2002, 7, 8), Sex::Female),
+            (NaiveDate::from_ymd(1899, 12, 31), Sex::Female),
+        ] {
+            let pesel = random_pesel(&mut rng, birth_date, sex);
+            assert_eq!(pesel.len(), 11);
+            assert_eq!(decode_birth_date(&pesel), Some(birth_date));
+            let sex_digit = pesel[9..10].parse::<u32>().unwrap();
+            match sex {
+                Sex::Male => assert_eq!(sex_digit % 2, 1),
+                Sex::Female => assert_eq!(sex_digit % 2, 0),
+            }
+        }
+        assert_eq!(
+            &random_pesel(&mut rng, NaiveDate::from_ymd(2002, 7, 8), Sex::Female)[..6],
+            "022708"
+        );
+    }
+
+    #[test]
+    fn corrupt_pesel_is_invalid() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..100 {
+            let birth_date = NaiveDate::from_ymd(1980 + rng.gen_range(0..40), 2, 28);
+            let pesel = random_pesel(&mut rng, birth_date, Sex::Male);
+            let corrupted = corrupt_pesel(&mut rng, &pesel);
+            assert_eq!(corrupted.len(), 11);
+            assert_eq!(decode_birth_date(&pesel), Some(birth_date));
+            assert_eq!(decode_birth_date(&corrupted), None);
+        }
+    }
+}
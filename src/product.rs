@@ -0,0 +1,55 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ProductKind {
+    Feed,
+    Crop,
+    AnimalProduct,
+    Meat,
+    Medicine,
+}
+
+#[derive(Serialize)]
+pub struct Product<'a> {
+    pub id: usize,
+    name: &'a str,
+    pub kind: ProductKind,
+    pub unit: &'a str,
+    pub volume_per_unit: f32,      // in cubic meters
+    pub unit_price: f32,           // list price, the actual one can vary
+    pub species_id: Option<usize>, // the species the product is made from or meant for, if any
+}
+
+impl<'a> Product<'a> {
+    pub const fn new(
+        id: usize,
+        name: &'a str,
+        kind: ProductKind,
+        unit: &'a str,
+        volume_per_unit: f32,
+        unit_price: f32,
+        species_id: Option<usize>,
+    ) -> Self {
+        Product {
+            id,
+            name,
+            kind,
+            unit,
+            volume_per_unit,
+            unit_price,
+            species_id,
+        }
+    }
+}
+
+// Falls back to a product of the kind that isn't tied to any species
+pub fn product_for_species<'a, 'b>(
+    products: &'b [Product<'a>],
+    kind: ProductKind,
+    species_id: usize,
+) -> Option<&'b Product<'a>> {
+    let of_kind = || products.iter().filter(move |p| p.kind == kind);
+    of_kind()
+        .find(|product| product.species_id == Some(species_id))
+        .or_else(|| of_kind().find(|product| product.species_id.is_none()))
+}
@@ -0,0 +1,149 @@
+use std::{collections::HashMap, f32::consts::PI};
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime};
+use rand::{distributions::Uniform, prelude::Distribution};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    customer::CustomerKind, health_report::HealthReport, herd::Herd,
+    livestock_movement::HerdMembership, parallel::generator_rng, product::Product,
+    snapshot::Expansion, table::Table,
+};
+
+const DAYS_IN_YEAR: f32 = 365.;
+const MAX_DAILY_VARIATION: f32 = 0.05;
+
+pub struct ProductionProfile {
+    pub species_id: usize,
+    pub product_id: usize,
+    pub yield_per_animal_per_day: f32, // in the product's unit
+    pub report_interval_days: i64,     // e.g. milking is reported daily, shearing twice a year
+    pub seasonal_amplitude: f32,       // relative to the mean yield
+    pub peak_day_of_year: u32,
+    pub customer_kinds: Vec<CustomerKind>, // who buys the product
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ProductionReport<'a> {
+    id: usize,
+    herd_id: usize,
+    pub date: NaiveDate,
+    pub product_id: usize,
+    pub quantity: f32,
+    #[serde(skip_deserializing)]
+    unit: &'a str,
+}
+
+impl<'a> ProductionReport<'a> {
+    pub const fn new(
+        id: usize,
+        herd: &Herd,
+        date: NaiveDate,
+        product: &Product<'a>,
+        quantity: f32,
+    ) -> Self {
+        ProductionReport {
+            id,
+            herd_id: herd.id,
+            date,
+            product_id: product.id,
+            quantity,
+            unit: product.unit,
+        }
+    }
+}
+
+// The units are saved, but as copies of the products' ones, which the loaded reports point to again
+pub fn restore_units<'a>(
+    production_reports: &mut [ProductionReport<'a>],
+    products: &[Product<'a>],
+) {
+    for report in production_reports.iter_mut() {
+        report.unit = products[report.product_id].unit;
+    }
+}
+
+pub fn expand_production_report_vec<'a>(
+    production_reports: &mut Table<ProductionReport<'a>>,
+    herds: &[Herd],
+    membership: &HerdMembership,
+    health_reports: &[HealthReport],
+    streamed_healthy_ratios: &HashMap<usize, (NaiveDateTime, f32)>, // per herd, of the latest one
+    products: &[Product<'a>],
+    expansion: &Expansion,
+) {
+    let (first_day, last_day) = (expansion.first_day(), expansion.last_day());
+    let mut rng = generator_rng();
+    let variation_distribution = Uniform::new_inclusive(-MAX_DAILY_VARIATION, MAX_DAILY_VARIATION);
+
+    let mut herd_health_reports: Vec<Vec<&HealthReport>> = herds.iter().map(|_| vec![]).collect();
+    for report in health_reports {
+        herd_health_reports[report.herd_id].push(report);
+    }
+    for reports in herd_health_reports.iter_mut() {
+        reports.sort_by_key(|report| report.timestamp);
+    }
+
+    for herd in herds {
+        for profile in expansion
+            .config
+            .production_profiles
+            .iter()
+            .filter(|p| p.species_id == herd.species_id)
+        {
+            let product = &products[profile.product_id];
+            // the reports follow the calendar rather than the expansion, so the interval between
+            // them holds across expansions; the herds are staggered over the interval
+            let interval = profile.report_interval_days;
+            let phase = (herd.id as i64 - first_day.num_days_from_ce() as i64).rem_euclid(interval);
+            let mut day = first_day + Duration::days(phase);
+            while day < last_day {
+                let moment = day.and_hms(0, 0, 0);
+                let living_count = membership.count_at(herd.id, moment);
+                let healthy_ratio = latest_healthy_ratio(
+                    &herd_health_reports[herd.id],
+                    streamed_healthy_ratios.get(&herd.id).copied(),
+                    moment,
+                );
+                let quantity = living_count as f32
+                    * healthy_ratio
+                    * profile.yield_per_animal_per_day
+                    * interval as f32
+                    * seasonal_factor(profile, day)
+                    * (1. + variation_distribution.sample(&mut rng));
+                if quantity > 0. {
+                    production_reports.push(ProductionReport::new(
+                        production_reports.next_id(),
+                        herd,
+                        day,
+                        product,
+                        quantity,
+                    ));
+                }
+                day += Duration::days(interval);
+            }
+        }
+    }
+}
+
+// Reports which were streamed out are only remembered by the latest one; herds which weren't
+// examined yet are assumed to be healthy
+fn latest_healthy_ratio(
+    sorted_reports: &[&HealthReport],
+    streamed: Option<(NaiveDateTime, f32)>,
+    moment: NaiveDateTime,
+) -> f32 {
+    let examined_count = sorted_reports.partition_point(|report| report.timestamp <= moment);
+    sorted_reports[..examined_count]
+        .last()
+        .map(|report| (report.timestamp, report.healthy_ratio()))
+        .into_iter()
+        .chain(streamed.filter(|&(timestamp, _)| timestamp <= moment))
+        .max_by_key(|&(timestamp, _)| timestamp)
+        .map_or(1., |(_, ratio)| ratio)
+}
+
+fn seasonal_factor(profile: &ProductionProfile, day: NaiveDate) -> f32 {
+    let days_from_peak = day.ordinal() as f32 - profile.peak_day_of_year as f32;
+    1. + profile.seasonal_amplitude * (2. * PI * days_from_peak / DAYS_IN_YEAR).cos()
+}
@@ -0,0 +1,204 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::{Duration, NaiveDate};
+use rand::{prelude::IteratorRandom, rngs::StdRng, Rng};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    parallel::generator_rng, product::Product, supplier::Supplier, table::Table,
+    warehouse::Warehouse,
+};
+
+const DAYS_IN_YEAR: f32 = 365.;
+
+pub struct PurchaseConfig {
+    pub delivery_delay_min_days: i64,
+    pub delivery_delay_max_days: i64,
+    pub yearly_price_drift: f32, // relative change of the list prices over a year
+    pub max_price_variation: f32, // between single orders
+    pub price_reference_date: NaiveDate, // when the list prices were current
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PurchaseOrder {
+    pub id: usize,
+    supplier_id: usize,
+    order_date: NaiveDate,
+    delivery_date: NaiveDate,
+}
+
+impl PurchaseOrder {
+    pub const fn new(
+        id: usize,
+        supplier: &Supplier,
+        order_date: NaiveDate,
+        delivery_date: NaiveDate,
+    ) -> Self {
+        PurchaseOrder {
+            id,
+            supplier_id: supplier.id,
+            order_date,
+            delivery_date,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PurchaseOrderLine {
+    pub id: usize,
+    purchase_order_id: usize,
+    product_id: usize,
+    warehouse_id: usize,
+    quantity: f32,
+    unit_price: f32,
+    total_price: f32,
+}
+
+impl PurchaseOrderLine {
+    pub fn new(
+        id: usize,
+        order: &PurchaseOrder,
+        product: &Product,
+        warehouse: &Warehouse,
+        quantity: f32,
+        unit_price: f32,
+    ) -> Self {
+        PurchaseOrderLine {
+            id,
+            purchase_order_id: order.id,
+            product_id: product.id,
+            warehouse_id: warehouse.id,
+            quantity,
+            unit_price,
+            total_price: (quantity * unit_price * 100.).round() / 100.,
+        }
+    }
+}
+
+// A delivery that was ordered but hasn't arrived yet
+pub struct PendingDelivery {
+    pub line_id: usize,
+    pub warehouse_id: usize,
+    pub product_id: usize,
+    pub quantity: f32,
+    pub date: NaiveDate,
+}
+
+impl PendingDelivery {
+    const fn new(line: &PurchaseOrderLine, date: NaiveDate) -> Self {
+        PendingDelivery {
+            line_id: line.id,
+            warehouse_id: line.warehouse_id,
+            product_id: line.product_id,
+            quantity: line.quantity,
+            date,
+        }
+    }
+}
+
+// Places the orders for the deliveries the warehouses need, which arrive some days after the
+// order; everything a supplier is asked for on one day to be delivered on another makes up a
+// single order
+pub struct Purchasing<'a> {
+    orders: &'a mut Table<PurchaseOrder>,
+    lines: &'a mut Table<PurchaseOrderLine>,
+    suppliers: &'a [Supplier],
+    config: &'a PurchaseConfig,
+    open_orders: HashMap<(usize, NaiveDate, NaiveDate), usize>, // by the orders' positions
+    rng: StdRng,
+}
+
+impl<'a> Purchasing<'a> {
+    pub fn new(
+        orders: &'a mut Table<PurchaseOrder>,
+        lines: &'a mut Table<PurchaseOrderLine>,
+        suppliers: &'a [Supplier],
+        config: &'a PurchaseConfig,
+    ) -> Self {
+        Purchasing {
+            orders,
+            lines,
+            suppliers,
+            config,
+            open_orders: HashMap::new(),
+            rng: generator_rng(),
+        }
+    }
+
+    // The longest time an order can take to arrive
+    pub fn max_delivery_delay(&self) -> Duration {
+        Duration::days(self.config.delivery_delay_max_days)
+    }
+
+    // The lines of the orders placed in the previous expansions which weren't delivered in them
+    pub fn pending_deliveries(&self, delivered_line_ids: &HashSet<usize>) -> Vec<PendingDelivery> {
+        self.lines
+            .iter()
+            .filter(|line| !delivered_line_ids.contains(&line.id))
+            .map(|line| {
+                let order = self.orders.by_id(line.purchase_order_id);
+                PendingDelivery::new(line, order.delivery_date)
+            })
+            .collect()
+    }
+
+    // Nothing is ordered when no supplier sells the product
+    pub fn order(
+        &mut self,
+        product: &Product,
+        warehouse: &Warehouse,
+        quantity: f32,
+        order_date: NaiveDate,
+    ) -> Option<PendingDelivery> {
+        let supplier = self
+            .suppliers
+            .iter()
+            .filter(|supplier| supplier.kind == product.kind)
+            .choose(&mut self.rng)?;
+        let delay = self
+            .rng
+            .gen_range(self.config.delivery_delay_min_days..=self.config.delivery_delay_max_days);
+        let delivery_date = order_date + Duration::days(delay);
+
+        let orders = &mut *self.orders;
+        let order_position = *self
+            .open_orders
+            .entry((supplier.id, order_date, delivery_date))
+            .or_insert_with(|| {
+                orders.push(PurchaseOrder::new(
+                    orders.next_id(),
+                    supplier,
+                    order_date,
+                    delivery_date,
+                ));
+                orders.len() - 1
+            });
+
+        let unit_price = self.unit_price(product, order_date);
+        self.lines.push(PurchaseOrderLine::new(
+            self.lines.next_id(),
+            &self.orders[order_position],
+            product,
+            warehouse,
+            quantity,
+            unit_price,
+        ));
+        Some(PendingDelivery::new(
+            self.lines.last().unwrap(),
+            delivery_date,
+        ))
+    }
+
+    // The list price drifts steadily away from the one at the reference date
+    fn unit_price(&mut self, product: &Product, order_date: NaiveDate) -> f32 {
+        let years = order_date
+            .signed_duration_since(self.config.price_reference_date)
+            .num_days() as f32
+            / DAYS_IN_YEAR;
+        let drift = (1. + self.config.yearly_price_drift).powf(years);
+        let variation = self
+            .rng
+            .gen_range(-self.config.max_price_variation..=self.config.max_price_variation);
+        (product.unit_price * drift * (1. + variation) * 100.).round() / 100.
+    }
+}
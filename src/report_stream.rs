@@ -0,0 +1,61 @@
+use std::{
+    collections::HashMap,
+    fs::{File, OpenOptions},
+    path::PathBuf,
+};
+
+use chrono::{Duration, NaiveDateTime};
+use serde::Serialize;
+
+// Writes the fact tables to disk while the history is being generated, so that only the rows the
+// later windows still need have to be held in memory
+pub struct ReportStream {
+    dir: PathBuf,
+    writers: HashMap<&'static str, csv::Writer<File>>,
+}
+
+impl ReportStream {
+    pub fn new(dir: &str) -> Self {
+        std::fs::create_dir_all(dir).unwrap();
+        ReportStream {
+            dir: PathBuf::from(dir),
+            writers: HashMap::new(),
+        }
+    }
+
+    // Appends the rows to the table's file, which is started afresh on the first write
+    pub fn write<T: Serialize>(&mut self, table: &'static str, rows: &[T]) {
+        let dir = &self.dir;
+        let writer = self.writers.entry(table).or_insert_with(|| {
+            let file = OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .open(dir.join(table).with_extension("csv"))
+                .unwrap();
+            csv::WriterBuilder::new()
+                .has_headers(false)
+                .from_writer(file)
+        });
+        for row in rows {
+            writer.serialize(row).unwrap();
+        }
+        writer.flush().unwrap();
+    }
+}
+
+// The dates split into windows of the given length, the last of which may be shorter
+pub fn stream_windows(
+    from_when: NaiveDateTime,
+    to_when: NaiveDateTime,
+    window: Duration,
+) -> Vec<(NaiveDateTime, NaiveDateTime)> {
+    let mut windows = vec![];
+    let mut window_start = from_when;
+    while window_start < to_when {
+        let window_end = (window_start + window).min(to_when);
+        windows.push((window_start, window_end));
+        window_start = window_end;
+    }
+    windows
+}
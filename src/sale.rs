@@ -0,0 +1,234 @@
+use std::collections::HashMap;
+
+use chrono::{Duration, NaiveDate};
+use rand::{seq::IteratorRandom, Rng};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    customer::{Customer, CustomerKind},
+    livestock::{DisposalPurpose, Livestock},
+    parallel::generator_rng,
+    product::Product,
+    production_report::ProductionReport,
+    snapshot::Expansion,
+    stock_movement::StockMovement,
+    table::Table,
+};
+
+const MAX_SALE_DELAY_DAYS: i64 = 3;
+const MAX_PRICE_VARIATION: f32 = 0.1;
+
+pub struct ButcheryProfile {
+    pub species_id: usize,
+    pub product_id: usize,
+    pub dressing_pct: f32, // of the live weight at the disposal that's left as the carcass
+    pub customer_kinds: Vec<CustomerKind>, // who buys the meat
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Sale {
+    pub id: usize,
+    customer_id: usize,
+    pub date: NaiveDate,
+}
+
+impl Sale {
+    pub const fn new(id: usize, customer: &Customer, date: NaiveDate) -> Self {
+        Sale {
+            id,
+            customer_id: customer.id,
+            date,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SaleLine {
+    id: usize,
+    sale_id: usize,
+    product_id: usize,
+    livestock_id: Option<usize>,
+    stock_movement_id: Option<usize>,
+    quantity: f32,
+    unit_price: f32,
+    total_price: f32,
+}
+
+impl SaleLine {
+    pub fn new(
+        id: usize,
+        sale: &Sale,
+        product: &Product,
+        livestock: Option<&Livestock>,
+        stock_movement: Option<&StockMovement>,
+        quantity: f32,
+        unit_price: f32,
+    ) -> Self {
+        SaleLine {
+            id,
+            sale_id: sale.id,
+            product_id: product.id,
+            livestock_id: livestock.map(|animal| animal.id),
+            stock_movement_id: stock_movement.map(|movement| movement.id),
+            quantity,
+            unit_price,
+            total_price: (quantity * unit_price * 100.).round() / 100.,
+        }
+    }
+}
+
+struct Goods<'a> {
+    available: NaiveDate,
+    product_id: usize,
+    livestock: Option<&'a Livestock>,
+    stock_movement: Option<&'a StockMovement>,
+    quantity: f32,
+    customer_kinds: &'a [CustomerKind],
+}
+
+// The customers along with the sales made to them, each grouping what one customer bought on one
+// day
+pub struct Selling<'a> {
+    sales: &'a mut Table<Sale>,
+    lines: &'a mut Table<SaleLine>,
+    customers: &'a [Customer],
+    products: &'a [Product<'a>],
+    open_sales: HashMap<(NaiveDate, usize), usize>, // by the sales' positions
+}
+
+impl<'a> Selling<'a> {
+    pub fn new(
+        sales: &'a mut Table<Sale>,
+        lines: &'a mut Table<SaleLine>,
+        customers: &'a [Customer],
+        products: &'a [Product<'a>],
+    ) -> Self {
+        Selling {
+            sales,
+            lines,
+            customers,
+            products,
+            open_sales: HashMap::new(),
+        }
+    }
+
+    // The goods go to a customer of the kind that buys them, or to anyone if there's none
+    fn sell<R: Rng>(&mut self, rng: &mut R, date: NaiveDate, goods: Goods) {
+        let customer = self
+            .customers
+            .iter()
+            .filter(|customer| goods.customer_kinds.contains(&customer.kind))
+            .choose(rng)
+            .unwrap_or_else(|| &self.customers[rng.gen_range(0..self.customers.len())]);
+        let sales = &mut *self.sales;
+        let sale_position = *self
+            .open_sales
+            .entry((date, customer.id))
+            .or_insert_with(|| {
+                sales.push(Sale::new(sales.next_id(), customer, date));
+                sales.len() - 1
+            });
+        let product = &self.products[goods.product_id];
+        let price_variation = rng.gen_range(1. - MAX_PRICE_VARIATION..=1. + MAX_PRICE_VARIATION);
+        let unit_price = product.unit_price * price_variation;
+        self.lines.push(SaleLine::new(
+            self.lines.next_id(),
+            &self.sales[sale_position],
+            product,
+            goods.livestock,
+            goods.stock_movement,
+            goods.quantity,
+            (unit_price * 100.).round() / 100.,
+        ));
+    }
+}
+
+// Carcasses of the animals butchered within the given days and all of the production output are
+// sold shortly after, and the stock taken out of the warehouses to be sold on the day it leaves
+pub fn expand_sale_vec(
+    selling: &mut Selling,
+    livestock: &[Livestock],
+    production_reports: &[ProductionReport],
+    stock_movements: &[StockMovement],
+    expansion: &Expansion,
+) {
+    let (first_day, last_day) = (expansion.first_day(), expansion.last_day());
+    let mut rng = generator_rng();
+
+    let mut goods = vec![];
+    for animal in livestock {
+        let disposal = match (animal.disposal, animal.disposal_purpose) {
+            (Some(disposal), Some(DisposalPurpose::Butcher)) => disposal.date(),
+            _ => continue,
+        };
+        if disposal < first_day || disposal >= last_day {
+            continue;
+        }
+        let profile = match expansion
+            .config
+            .butchery_profiles
+            .iter()
+            .find(|profile| profile.species_id == animal.species_id)
+        {
+            Some(profile) => profile,
+            None => continue,
+        };
+        let weight = match animal.disposal_weight {
+            Some(weight) => weight,
+            None => continue,
+        };
+        goods.push(Goods {
+            available: disposal.succ(),
+            product_id: profile.product_id,
+            livestock: Some(animal),
+            stock_movement: None,
+            quantity: (weight * profile.dressing_pct * 10.).round() / 10.,
+            customer_kinds: &profile.customer_kinds,
+        });
+    }
+    for report in production_reports {
+        let customer_kinds = expansion
+            .config
+            .production_profiles
+            .iter()
+            .find(|profile| profile.product_id == report.product_id)
+            .map_or(&[][..], |profile| &profile.customer_kinds);
+        goods.push(Goods {
+            available: report.date,
+            product_id: report.product_id,
+            livestock: None,
+            stock_movement: None,
+            quantity: report.quantity,
+            customer_kinds,
+        });
+    }
+    for movement in stock_movements.iter().filter(|movement| movement.is_sale()) {
+        goods.push(Goods {
+            available: movement.timestamp.date(),
+            product_id: movement.product_id,
+            livestock: None,
+            stock_movement: Some(movement),
+            quantity: movement.quantity,
+            customer_kinds: &expansion.config.crop_customer_kinds,
+        });
+    }
+
+    let mut dated_goods: Vec<(NaiveDate, Goods)> = goods
+        .into_iter()
+        .map(|goods| {
+            let delay = match goods.stock_movement {
+                Some(_) => Duration::zero(),
+                None => Duration::days(rng.gen_range(0..=MAX_SALE_DELAY_DAYS)),
+            };
+            let date = (goods.available + delay)
+                .min(last_day.pred())
+                .max(goods.available);
+            (date, goods)
+        })
+        .collect();
+    dated_goods.sort_by_key(|(date, _)| *date);
+
+    for (date, goods) in dated_goods {
+        selling.sell(&mut rng, date, goods);
+    }
+}
@@ -0,0 +1,260 @@
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, Weekday};
+use rand::{prelude::SliceRandom, Rng};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    employee::{Employee, EmployeeRole},
+    parallel::generator_rng,
+    table::Table,
+};
+
+const SHIFT_HOURS: i64 = 8;
+const FIRST_SHIFT_START_HOUR: i64 = 6;
+const OFFICE_SHIFT_START_HOUR: i64 = 8;
+const WORK_DAYS_PER_WEEK: i64 = 5;
+const MAX_OVERTIME_HOURS: i64 = 4;
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub enum ShiftLocation {
+    Field,
+    Clinic,
+    Warehouse,
+    Office,
+}
+
+impl ShiftLocation {
+    pub const fn for_role(role: EmployeeRole) -> Self {
+        match role {
+            EmployeeRole::Veterinarian => ShiftLocation::Clinic,
+            EmployeeRole::Herdsman => ShiftLocation::Field,
+            EmployeeRole::WarehouseManager => ShiftLocation::Warehouse,
+            EmployeeRole::Administrator => ShiftLocation::Office,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Shift {
+    pub id: usize,
+    pub employee_id: usize,
+    pub start: NaiveDateTime,
+    pub end: NaiveDateTime,
+    location: ShiftLocation,
+}
+
+impl Shift {
+    pub const fn new(
+        id: usize,
+        employee: &Employee,
+        start: NaiveDateTime,
+        end: NaiveDateTime,
+        location: ShiftLocation,
+    ) -> Self {
+        Shift {
+            id,
+            employee_id: employee.id,
+            start,
+            end,
+            location,
+        }
+    }
+
+    pub fn overtime(&self) -> Duration {
+        (self.end.signed_duration_since(self.start) - Duration::hours(SHIFT_HOURS))
+            .max(Duration::zero())
+    }
+}
+
+pub fn expand_shift_vec(
+    shifts: &mut Table<Shift>,
+    employees: &[Employee],
+    first_day: NaiveDate,
+    last_day: NaiveDate,
+    overtime_pct: f32,
+) {
+    let mut rng = generator_rng();
+
+    let mut day = first_day;
+    while day < last_day {
+        // the rotation follows the calendar, so it carries on from one expansion to the next
+        let day_idx = day.num_days_from_ce() as i64;
+        let midnight = day.and_hms(0, 0, 0);
+        for employee in employees.iter().filter(|e| e.is_employed_on(day)) {
+            let start = match employee.role {
+                // field staff rotate through the three shifts of the day, a week at a time, and have
+                // their days off staggered so the herds are never left unattended
+                EmployeeRole::Veterinarian | EmployeeRole::Herdsman => {
+                    let staggered_day = day_idx + employee.id as i64;
+                    if staggered_day % 7 >= WORK_DAYS_PER_WEEK {
+                        continue;
+                    }
+                    let rotation = (day_idx / 7 + employee.id as i64) % (24 / SHIFT_HOURS);
+                    midnight + Duration::hours(FIRST_SHIFT_START_HOUR + rotation * SHIFT_HOURS)
+                }
+                EmployeeRole::WarehouseManager | EmployeeRole::Administrator => {
+                    if matches!(day.weekday(), Weekday::Sat | Weekday::Sun) {
+                        continue;
+                    }
+                    midnight + Duration::hours(OFFICE_SHIFT_START_HOUR)
+                }
+            };
+            let mut end = start + Duration::hours(SHIFT_HOURS);
+            if rng.gen_bool(overtime_pct as f64) {
+                end += Duration::hours(rng.gen_range(1..=MAX_OVERTIME_HOURS));
+            }
+            shifts.push(Shift::new(
+                shifts.next_id(),
+                employee,
+                start,
+                end,
+                ShiftLocation::for_role(employee.role),
+            ));
+        }
+        day = day.succ();
+    }
+}
+
+// Field staff shifts are numbered from the first one of the day
+pub const fn field_shift_of(hour: u32) -> u32 {
+    let hours_since_first = (hour as i64 + 24 - FIRST_SHIFT_START_HOUR) % 24;
+    (hours_since_first / SHIFT_HOURS) as u32 + 1
+}
+
+// Shifts of a single role sorted by their start, for looking up who's on duty
+pub struct Roster<'a> {
+    shifts: Vec<&'a Shift>,
+    employees: &'a [Employee],
+}
+
+impl<'a> Roster<'a> {
+    pub fn new(shifts: &'a [Shift], employees: &'a [Employee], role: EmployeeRole) -> Self {
+        let mut shifts: Vec<&Shift> = shifts
+            .iter()
+            .filter(|shift| employees[shift.employee_id].role == role)
+            .collect();
+        shifts.sort_by_key(|shift| shift.start);
+        Roster { shifts, employees }
+    }
+
+    // Picks an employee on a shift overlapping the given window and a moment within both; if
+    // nobody is on duty then, the closest shift of someone still employed at the moment is taken,
+    // the moment being moved as close to its start as the window allows, so it never leaves the
+    // window. Nobody is picked when nobody of the role is employed then
+    pub fn assign<R: Rng>(
+        &self,
+        rng: &mut R,
+        window_start: NaiveDateTime,
+        window_end: NaiveDateTime,
+    ) -> Option<(&'a Employee, NaiveDateTime)> {
+        let earliest_start = window_start - Duration::hours(SHIFT_HOURS + MAX_OVERTIME_HOURS);
+        let first = self
+            .shifts
+            .partition_point(|shift| shift.start <= earliest_start);
+        let last = self
+            .shifts
+            .partition_point(|shift| shift.start < window_end);
+        let on_duty: Vec<&Shift> = self.shifts[first..last]
+            .iter()
+            .copied()
+            .filter(|shift| shift.end > window_start)
+            .collect();
+
+        if let Some(shift) = on_duty.choose(rng) {
+            let from = shift.start.max(window_start);
+            let to = shift.end.min(window_end);
+            // the overlap can be shorter than a second when the window isn't aligned to it
+            let overlap_seconds = to.signed_duration_since(from).num_seconds().max(1);
+            let offset = rng.gen_range(0..overlap_seconds);
+            return Some((
+                &self.employees[shift.employee_id],
+                from + Duration::seconds(offset),
+            ));
+        }
+        // the shifts after the window first, then the ones before it from the latest
+        let latest = (window_end - Duration::seconds(1)).max(window_start);
+        self.shifts[last..]
+            .iter()
+            .chain(self.shifts[..last].iter().rev())
+            .map(|shift| {
+                (
+                    &self.employees[shift.employee_id],
+                    shift.start.clamp(window_start, latest),
+                )
+            })
+            .find(|(employee, moment)| employee.is_employed_on(moment.date()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, NaiveDateTime};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+    use crate::employee::{Identity, Sex};
+
+    fn at(day: u32, hour: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd(2024, 3, day).and_hms(hour, 0, 0)
+    }
+
+    fn vet(id: usize) -> Employee {
+        let identity = Identity {
+            name: "Anna".to_string(),
+            surname: "Nowak".to_string(),
+            sex: Sex::Female,
+            birth_date: NaiveDate::from_ymd(1980, 1, 1),
+            pesel: "80010100008".to_string(),
+        };
+        Employee::new(
+            id,
+            identity,
+            String::new(),
+            10_000.,
+            EmployeeRole::Veterinarian,
+            NaiveDate::from_ymd(2024, 1, 1),
+        )
+    }
+
+    #[test]
+    fn assigns_moments_within_the_window() {
+        let employees = [vet(0)];
+        let shifts = [
+            Shift::new(0, &employees[0], at(4, 6), at(4, 14), ShiftLocation::Clinic),
+            Shift::new(1, &employees[0], at(8, 6), at(8, 14), ShiftLocation::Clinic),
+        ];
+        let roster = Roster::new(&shifts, &employees, EmployeeRole::Veterinarian);
+        let mut rng = StdRng::seed_from_u64(0);
+
+        // on duty
+        let (_, moment) = roster.assign(&mut rng, at(4, 0), at(5, 0)).unwrap();
+        assert!(moment >= at(4, 6) && moment < at(4, 14));
+        // nobody on duty, the next shift starts after the window
+        let (_, moment) = roster.assign(&mut rng, at(6, 0), at(7, 0)).unwrap();
+        assert_eq!(moment, at(7, 0) - Duration::seconds(1));
+        // nobody on duty, and no shift after the window either
+        let (_, moment) = roster.assign(&mut rng, at(10, 0), at(11, 0)).unwrap();
+        assert_eq!(moment, at(10, 0));
+    }
+
+    #[test]
+    fn assigns_nobody_dismissed_by_then() {
+        let mut employees = [vet(0), vet(1)];
+        employees[0].termination_date = Some(NaiveDate::from_ymd(2024, 3, 4));
+        employees[1].termination_date = Some(NaiveDate::from_ymd(2024, 3, 8));
+        let shifts = [
+            Shift::new(0, &employees[1], at(2, 6), at(2, 14), ShiftLocation::Clinic),
+            Shift::new(1, &employees[0], at(4, 6), at(4, 14), ShiftLocation::Clinic),
+        ];
+        let roster = Roster::new(&shifts, &employees, EmployeeRole::Veterinarian);
+        let mut rng = StdRng::seed_from_u64(0);
+
+        // the latest shift is of someone dismissed by then, so an earlier one's taken
+        let (vet, _) = roster.assign(&mut rng, at(6, 0), at(7, 0)).unwrap();
+        assert_eq!(vet.id, 1);
+        // everybody's been dismissed
+        assert!(roster.assign(&mut rng, at(10, 0), at(11, 0)).is_none());
+        // nobody ever worked a shift
+        let roster = Roster::new(&[], &employees, EmployeeRole::Veterinarian);
+        assert!(roster.assign(&mut rng, at(4, 0), at(5, 0)).is_none());
+    }
+}
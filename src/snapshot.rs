@@ -1,145 +1,815 @@
-use std::{fs::OpenOptions, ops::Deref, path::Path};
+use std::{collections::HashMap, fs::OpenOptions, ops::Deref, path::Path, thread};
 
-use chrono::{Duration, NaiveDateTime};
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime};
 use enum_map::EnumMap;
-use serde::Serialize;
+use serde::{de::DeserializeOwned, Serialize};
 
 use crate::{
-    employee::*, feeding_report::*, headcount_report::*, health_report::*, herd::*, livestock::*,
-    pasture::*, species::*, warehouse::*,
+    aggregate::*, calendar::*, crop::*, customer::*, employee::*, feeding_report::*, field::*,
+    headcount_report::*, health_report::*, herd::*, late_arrival::*, livestock::*,
+    livestock_movement::*, pasture::*, payroll::*, product::*, production_report::*,
+    purchase_order::*, report_stream::*, sale::*, shift::*, species::*, star_schema::*,
+    stock_movement::*, supplier::*, table::*, treatment::*, warehouse::*, weather::*,
 };
 
+// How the farm is run, the same for every expansion unless the farm changes in between
+pub struct FarmConfig {
+    pub report_interval: Duration,
+    pub pasture_size_ranges: EnumMap<PastureKind, PastureAreaMinMax>,
+    pub species_for_herds: Vec<usize>,
+    pub max_herds_per_pasture: usize,
+    pub stocking_pct: f32, // of a new herd's capacity
+    pub field_area_min: f32,
+    pub field_area_max: f32,
+    pub species_for_fields: Vec<usize>,
+    pub hiring: HiringConfig,
+    pub overtime_pct: f32, // chance of a shift running over
+    pub payroll_bonus: BonusConfig,
+    pub purchase: PurchaseConfig,
+    pub feed_delivery_quantity: f32,
+    pub medicine_delivery_quantity: f32,
+    pub disposal_profiles: Vec<DisposalProfile>,
+    pub illness: IllnessConfig,
+    pub production_profiles: Vec<ProductionProfile>,
+    pub butchery_profiles: Vec<ButcheryProfile>,
+    pub crop_customer_kinds: Vec<CustomerKind>,
+    pub late_arrivals: Option<LateArrivalConfig>,
+}
+
+// What the farm gains and loses over a single expansion
+pub struct Growth {
+    pub new_pasture_count: usize,
+    pub new_field_count: usize,
+    pub hired_employee_counts: EnumMap<EmployeeRole, usize>,
+    pub dismiss_pct: f32, // chance of every employee being dismissed
+    pub new_warehouse_count: usize,
+    pub new_supplier_count: usize,
+    pub new_customer_count: usize,
+}
+
+// The stretch of history being generated, for the generators which need more than its bounds
+pub struct Expansion<'a> {
+    pub from_when: NaiveDateTime,
+    pub to_when: NaiveDateTime,
+    pub config: &'a FarmConfig,
+}
+
+impl Expansion<'_> {
+    pub fn first_day(&self) -> NaiveDate {
+        self.from_when.date()
+    }
+
+    pub fn last_day(&self) -> NaiveDate {
+        self.to_when.date()
+    }
+}
+
 pub struct Snapshot {
     pub pastures: Vec<Pasture>,
     pub species: Vec<Species<'static>>,
+    pub products: Vec<Product<'static>>,
     pub herds: Vec<Herd>,
-    pub feeding_reports: Vec<FeedingReport>,
-    pub livestock: Vec<Livestock>,
-    pub employees: Vec<Employee<'static, 'static>>,
-    pub health_reports: Vec<HealthReport>,
+    pub feeding_reports: Table<FeedingReport>,
+    pub livestock: Table<Livestock>,
+    pub employees: Vec<Employee>,
+    pub health_reports: Table<HealthReport>,
     pub warehouses: Vec<Warehouse>,
-    pub headcount_reports: Vec<HeadcountReport>,
+    pub headcount_reports: Table<HeadcountReport>,
+    pub shifts: Table<Shift>,
+    pub payrolls: Table<Payroll>,
+    pub stock_movements: Table<StockMovement>,
+    pub fields: Vec<Field>,
+    pub crops: Vec<Crop>,
+    pub harvests: Vec<Harvest>,
+    pub production_reports: Table<ProductionReport<'static>>,
+    pub customers: Vec<Customer>,
+    pub sales: Table<Sale>,
+    pub sale_lines: Table<SaleLine>,
+    pub suppliers: Vec<Supplier>,
+    pub purchase_orders: Table<PurchaseOrder>,
+    pub purchase_order_lines: Table<PurchaseOrderLine>,
+    pub treatments: Table<Treatment>,
+    pub livestock_movements: Table<LivestockMovement>,
+    pub weather: Vec<Weather>,
+    pub dim_dates: Vec<DimDate>,
+    pub dim_times: Vec<DimTime>, // optional, left empty unless filled in
+
+    pub as_of: Option<NaiveDateTime>, // records which arrive after this aren't saved
+
+    // what the next expansion carries on from, which the tables it's derived from may no longer
+    // hold in full once a stream dropped their rows
+    stock_state: Option<StockState>, // worked out from the tables when first needed
+    feeder_fills: Vec<f32>,          // per pasture
+    planned_sowings: Vec<Option<NaiveDate>>, // per field
+    streamed_healthy_ratios: HashMap<usize, (NaiveDateTime, f32)>, // per herd, of its latest report
 }
 
 impl Snapshot {
-    pub const fn new() -> Self {
+    pub fn new() -> Self {
         Snapshot {
             pastures: vec![],
             species: vec![],
+            products: vec![],
             herds: vec![],
-            feeding_reports: vec![],
-            livestock: vec![],
+            feeding_reports: Table::new(),
+            livestock: Table::new(),
             employees: vec![],
-            health_reports: vec![],
+            health_reports: Table::new(),
             warehouses: vec![],
-            headcount_reports: vec![],
+            headcount_reports: Table::new(),
+            shifts: Table::new(),
+            payrolls: Table::new(),
+            stock_movements: Table::new(),
+            fields: vec![],
+            crops: vec![],
+            harvests: vec![],
+            production_reports: Table::new(),
+            customers: vec![],
+            sales: Table::new(),
+            sale_lines: Table::new(),
+            suppliers: vec![],
+            purchase_orders: Table::new(),
+            purchase_order_lines: Table::new(),
+            treatments: Table::new(),
+            livestock_movements: Table::new(),
+            weather: vec![],
+            dim_dates: vec![],
+            dim_times: vec![],
+            as_of: None,
+            stock_state: None,
+            feeder_fills: vec![],
+            planned_sowings: vec![],
+            streamed_healthy_ratios: HashMap::new(),
+        }
+    }
+
+    // Reads back a snapshot written by save_to_dir, to expand it further. The species and products
+    // are only saved by name, so they're given like for a new snapshot, and have to be the ones it
+    // was generated with; what else isn't saved is derived again from the loaded tables. The
+    // reports which hadn't arrived by the time it was saved are gone for good.
+    pub fn load_from_dir(
+        dir: &str,
+        species: Vec<Species<'static>>,
+        products: Vec<Product<'static>>,
+    ) -> Result<Self, String> {
+        let dir = Path::new(dir);
+        if count_rows(dir.join("species").with_extension("csv"))? != species.len() {
+            return Err("the species don't match the snapshot's".to_string());
         }
+        if count_rows(dir.join("product").with_extension("csv"))? != products.len() {
+            return Err("the products don't match the snapshot's".to_string());
+        }
+
+        let mut ss = Snapshot::new();
+        ss.species = species;
+        ss.products = products;
+        ss.pastures = load_from_file(dir.join("pasture").with_extension("csv"))?;
+        ss.herds = load_from_file(dir.join("herd").with_extension("csv"))?;
+        ss.feeding_reports = Table::loaded(
+            load_from_file(dir.join("feeding_report").with_extension("csv"))?,
+            |report| report.id,
+        );
+        ss.livestock = load_from_file(dir.join("livestock").with_extension("csv"))?.into();
+        ss.employees = load_from_file(dir.join("employee").with_extension("csv"))?;
+        ss.health_reports = Table::loaded(
+            load_from_file(dir.join("health_report").with_extension("csv"))?,
+            |report| report.id,
+        );
+        ss.warehouses = load_from_file(dir.join("warehouse").with_extension("csv"))?;
+        ss.headcount_reports = Table::loaded(
+            load_from_file(dir.join("headcount_report").with_extension("csv"))?,
+            |report| report.id,
+        );
+        ss.shifts = load_from_file(dir.join("shift").with_extension("csv"))?.into();
+        ss.payrolls = load_from_file(dir.join("payroll").with_extension("csv"))?.into();
+        ss.stock_movements =
+            load_from_file(dir.join("stock_movement").with_extension("csv"))?.into();
+        ss.fields = load_from_file(dir.join("field").with_extension("csv"))?;
+        ss.crops = load_from_file(dir.join("crop").with_extension("csv"))?;
+        ss.harvests = load_from_file(dir.join("harvest").with_extension("csv"))?;
+        ss.production_reports =
+            load_from_file(dir.join("production_report").with_extension("csv"))?.into();
+        ss.customers = load_from_file(dir.join("customer").with_extension("csv"))?;
+        ss.sales = load_from_file(dir.join("sale").with_extension("csv"))?.into();
+        ss.sale_lines = load_from_file(dir.join("sale_line").with_extension("csv"))?.into();
+        ss.suppliers = load_from_file(dir.join("supplier").with_extension("csv"))?;
+        ss.purchase_orders =
+            load_from_file(dir.join("purchase_order").with_extension("csv"))?.into();
+        ss.purchase_order_lines =
+            load_from_file(dir.join("purchase_order_line").with_extension("csv"))?.into();
+        ss.treatments = load_from_file(dir.join("treatment").with_extension("csv"))?.into();
+        ss.livestock_movements =
+            load_from_file(dir.join("livestock_movement").with_extension("csv"))?.into();
+        ss.weather = load_from_file(dir.join("weather").with_extension("csv"))?;
+
+        mark_arrived(&mut ss.feeding_reports);
+        mark_arrived(&mut ss.headcount_reports);
+        mark_arrived(&mut ss.health_reports);
+        restore_herd_capacities(&mut ss.headcount_reports, &ss.herds);
+        restore_planned_harvest_dates(&mut ss.crops, &ss.species);
+        restore_units(&mut ss.production_reports, &ss.products);
+        restore_anomalies(&mut ss.weather);
+        if let (Some(first), Some(last)) = (ss.weather.first(), ss.weather.last()) {
+            expand_dim_date_vec(&mut ss.dim_dates, first.date, last.date);
+        }
+        if dir.join("dim_time").with_extension("csv").exists() {
+            ss.dim_times = dim_time_vec();
+        }
+        update_feeder_fills(&mut ss.feeder_fills, &ss.feeding_reports, &ss.pastures);
+        Ok(ss)
+    }
+
+    // When the snapshot was taken, as near as its tables tell: the weather goes up to the day it
+    // was taken on, and the last report on that day came before it; None for an empty snapshot
+    pub fn taken_at(&self) -> Option<NaiveDateTime> {
+        let last_day = self.weather.last()?.date.and_hms(0, 0, 0);
+        let report_times = (self
+            .feeding_reports
+            .iter()
+            .map(|report| report.occurred_at()))
+        .chain(
+            self.headcount_reports
+                .iter()
+                .map(|report| report.occurred_at()),
+        )
+        .chain(
+            self.health_reports
+                .iter()
+                .map(|report| report.occurred_at()),
+        );
+        Some(report_times.fold(last_day, NaiveDateTime::max))
     }
 
     pub fn expand(
         &mut self,
         from_when: NaiveDateTime,
         to_when: NaiveDateTime,
-        reports_interval: Duration,
-        new_pastures: usize,
-        pasture_ranges: &EnumMap<PastureKind, PastureAreaMinMax>,
-        species_for_herds: &[usize],
-        hired_employees_count: usize,
-        employee_names: &[&'static str],
-        employee_surnames: &[&'static str],
-        employee_salary_min: f32,
-        employee_salary_max: f32,
-        new_warehouse_count: usize,
-        kill_off_pct: f32,
-        headcount_min_count: u32,
-        headcount_max_count: u32,
-        ill_max_pct: f32,
-        severly_ill_max_pct: f32,
-        terminal_max_pct: f32,
+        config: &FarmConfig,
+        growth: &Growth,
     ) {
+        let expansion = Expansion {
+            from_when,
+            to_when,
+            config,
+        };
         let old_pasture_count = self.pastures.len(); // we're only generating herds for new pastures
         let old_headcount_report_count = self.headcount_reports.len(); // only generate health reports for new headcounts
-        expand_pasture_vec(&mut self.pastures, new_pastures, &pasture_ranges);
+        let old_health_report_count = self.health_reports.len();
+        let old_feeding_report_count = self.feeding_reports.len();
+        let old_harvest_count = self.harvests.len();
+        let old_production_report_count = self.production_reports.len();
+        let old_livestock_movement_count = self.livestock_movements.len();
+        let old_stock_movement_count = self.stock_movements.len();
+        let old_treatment_count = self.treatments.len();
+        expand_weather_vec(&mut self.weather, from_when.date(), to_when.date());
+        expand_dim_date_vec(&mut self.dim_dates, from_when.date(), to_when.date());
+        expand_pasture_vec(
+            &mut self.pastures,
+            growth.new_pasture_count,
+            &config.pasture_size_ranges,
+        );
         expand_herd_vec(
             &mut self.herds,
             &self.pastures[old_pasture_count..],
             &self.species,
-            species_for_herds,
+            &config.species_for_herds,
+            config.max_herds_per_pasture,
         );
         expand_feeding_report_vec(
             &mut self.feeding_reports,
+            &self.feeder_fills,
             &self.pastures,
+            &self.weather,
             from_when,
             to_when,
-            reports_interval,
+            config.report_interval,
         );
         expand_employee_vec(
             &mut self.employees,
-            hired_employees_count,
-            employee_names,
-            employee_surnames,
-            employee_salary_min,
-            employee_salary_max,
+            &growth.hired_employee_counts,
+            &config.hiring,
+            from_when.date(),
+        );
+        let vacancies = dismiss_employees(
+            &mut self.employees,
+            growth.dismiss_pct,
+            from_when.date(),
+            to_when.date(),
+        );
+        hire_employees(&mut self.employees, &vacancies, &config.hiring);
+        expand_shift_vec(
+            &mut self.shifts,
+            &self.employees,
+            from_when.date(),
+            to_when.date(),
+            config.overtime_pct,
+        );
+        expand_payroll_vec(
+            &mut self.payrolls,
+            &self.employees,
+            &self.shifts,
+            from_when.date(),
+            to_when.date(),
+            &config.payroll_bonus,
+        );
+        expand_field_vec(
+            &mut self.fields,
+            growth.new_field_count,
+            config.field_area_min,
+            config.field_area_max,
+        );
+        expand_crop_vec(
+            &mut self.crops,
+            &mut self.harvests,
+            &mut self.planned_sowings,
+            &self.fields,
+            &self.species,
+            &expansion,
         );
-        expand_warehouse_vec(&mut self.warehouses, new_warehouse_count, &self.employees);
         expand_livestock(
             &mut self.livestock,
             &self.herds,
             &self.species,
+            config.stocking_pct,
+            from_when,
+            to_when,
+        );
+        kill_off_livestock_vec(
+            &mut self.livestock,
+            &self.species,
+            &config.disposal_profiles,
+            from_when,
+            to_when,
+        );
+        butcher_livestock_vec(
+            &mut self.livestock,
+            &self.species,
+            &config.disposal_profiles,
+            to_when,
+        );
+        expand_livestock_movement_vec(
+            &mut self.livestock_movements,
+            &self.livestock,
+            &self.herds,
             &self.pastures,
             from_when,
             to_when,
         );
-        kill_off_livestock_vec(&mut self.livestock, kill_off_pct, &self.species, to_when);
-        butcher_livestock_vec(&mut self.livestock, &self.species, to_when);
+        let membership =
+            HerdMembership::new(&self.livestock, &self.livestock_movements, &self.herds);
+        let herdsmen = Roster::new(&self.shifts, &self.employees, EmployeeRole::Herdsman);
+        let vets = Roster::new(&self.shifts, &self.employees, EmployeeRole::Veterinarian);
         expand_headcount_report_vec(
             &mut self.headcount_reports,
             &self.herds,
-            &self.employees,
-            headcount_min_count,
-            headcount_max_count,
-            from_when,
-            to_when,
-            reports_interval,
+            &membership,
+            &herdsmen,
+            &expansion,
         );
         expand_health_report_vec_for_headcount_vec(
             &mut self.health_reports,
             &self.headcount_reports[old_headcount_report_count..],
+            &vets,
+            &self.herds,
+            &self.weather,
+            &expansion,
+        );
+        expand_warehouse_vec(
+            &mut self.warehouses,
+            growth.new_warehouse_count,
             &self.employees,
+        );
+        expand_supplier_vec(
+            &mut self.suppliers,
+            growth.new_supplier_count,
+            &[ProductKind::Feed, ProductKind::Medicine],
+            &config.hiring.name_packs,
+        );
+        expand_treatment_vec(
+            &mut self.treatments,
+            &membership,
+            &self.livestock_movements[old_livestock_movement_count..],
+            &self.health_reports[old_health_report_count..],
+            &self.products,
+            &vets,
+            &expansion,
+        );
+        let purchasing = Purchasing::new(
+            &mut self.purchase_orders,
+            &mut self.purchase_order_lines,
+            &self.suppliers,
+            &config.purchase,
+        );
+        if self.stock_state.is_none() {
+            self.stock_state = Some(StockState::new(
+                &self.stock_movements,
+                &purchasing,
+                &self.products,
+                &self.warehouses,
+                from_when,
+            ));
+        }
+        expand_stock_movement_vec(
+            &mut Stockroom::new(
+                &mut self.stock_movements,
+                self.stock_state.as_mut().unwrap(),
+                purchasing,
+                &self.products,
+                &self.warehouses,
+            ),
+            &mut self.feeding_reports[old_feeding_report_count..],
+            &self.harvests[old_harvest_count..],
+            &self.treatments[old_treatment_count..],
+            &self.pastures,
+            &self.herds,
+            &expansion,
+        );
+        // the refills are cut short when the warehouses run out
+        update_feeder_fills(
+            &mut self.feeder_fills,
+            &self.feeding_reports[old_feeding_report_count..],
+            &self.pastures,
+        );
+        expand_production_report_vec(
+            &mut self.production_reports,
             &self.herds,
-            ill_max_pct,
-            severly_ill_max_pct,
-            terminal_max_pct,
+            &membership,
+            &self.health_reports,
+            &self.streamed_healthy_ratios,
+            &self.products,
+            &expansion,
+        );
+        expand_customer_vec(
+            &mut self.customers,
+            growth.new_customer_count,
+            &config.hiring.name_packs,
+        );
+        expand_sale_vec(
+            &mut Selling::new(
+                &mut self.sales,
+                &mut self.sale_lines,
+                &self.customers,
+                &self.products,
+            ),
+            &self.livestock,
+            &self.production_reports[old_production_report_count..],
+            &self.stock_movements[old_stock_movement_count..],
+            &expansion,
         );
+        if let Some(late_arrivals) = &config.late_arrivals {
+            // corrections only make sense for rows that could have been emitted before
+            correct_headcount_records(
+                &mut self.headcount_reports[..old_headcount_report_count],
+                &mut self.health_reports[..old_health_report_count],
+                late_arrivals,
+                from_when,
+                to_when,
+            );
+            correct_records(
+                &mut self.health_reports[..old_health_report_count],
+                late_arrivals,
+                from_when,
+                to_when,
+            );
+            correct_records(
+                &mut self.feeding_reports[..old_feeding_report_count],
+                late_arrivals,
+                from_when,
+                to_when,
+            );
+            delay_records(
+                &mut self.headcount_reports[old_headcount_report_count..],
+                late_arrivals,
+            );
+            delay_records(
+                &mut self.health_reports[old_health_report_count..],
+                late_arrivals,
+            );
+            delay_records(
+                &mut self.feeding_reports[old_feeding_report_count..],
+                late_arrivals,
+            );
+        }
+        self.as_of = Some(to_when);
     }
 
     pub fn save_to_dir(&self, dir: &str) {
+        self.save_reports_to_dir(dir);
+        self.save_fact_tables_to_dir(dir);
+        self.save_held_tables_to_dir(dir);
+    }
+
+    // The reports which arrived by the time of the snapshot
+    fn save_reports_to_dir(&self, dir: &str) {
+        let dir = Path::new(dir);
+        // every table is written by its own thread
+        thread::scope(|scope| {
+            scope.spawn(|| {
+                save_to_file(
+                    dir.join("feeding_report").with_extension("csv"),
+                    &arrived_by(&self.feeding_reports, self.as_of),
+                )
+            });
+            scope.spawn(|| {
+                save_to_file(
+                    dir.join("health_report").with_extension("csv"),
+                    &arrived_by(&self.health_reports, self.as_of),
+                )
+            });
+            scope.spawn(|| {
+                save_to_file(
+                    dir.join("headcount_report").with_extension("csv"),
+                    &arrived_by(&self.headcount_reports, self.as_of),
+                )
+            });
+        });
+    }
+
+    // Appends what later expansions can't change any more to the stream's files, and drops from
+    // memory what they don't need: the reports which arrived by the time of the snapshot, the
+    // animals disposed of by then along with their movements, and the rest of the facts but for
+    // the shifts and payroll the coming payroll is worked out from
+    pub fn stream_tables(&mut self, stream: &mut ReportStream) {
+        let as_of = self.as_of.unwrap();
+        let held = |recorded_at: NaiveDateTime| recorded_at > as_of;
+        stream.write(
+            "feeding_report",
+            &self
+                .feeding_reports
+                .drop_unless(|report| held(report.recorded_at())),
+        );
+        stream.write(
+            "headcount_report",
+            &self
+                .headcount_reports
+                .drop_unless(|report| held(report.recorded_at())),
+        );
+        let health_reports = self
+            .health_reports
+            .drop_unless(|report| held(report.recorded_at()));
+        for report in health_reports.iter() {
+            let latest = self
+                .streamed_healthy_ratios
+                .entry(report.herd_id)
+                .or_insert((report.timestamp, report.healthy_ratio()));
+            if latest.0 < report.timestamp {
+                *latest = (report.timestamp, report.healthy_ratio());
+            }
+        }
+        stream.write("health_report", &health_reports);
+
+        stream.write(
+            "livestock",
+            &self
+                .livestock
+                .drop_unless(|animal| animal.disposal.is_none_or(|disposal| disposal > as_of)),
+        );
+        stream.write(
+            "livestock_movement",
+            self.livestock_movements.take_unwritten(),
+        );
+        let livestock = &self.livestock;
+        self.livestock_movements.drop_unless(|movement| {
+            livestock
+                .binary_search_by_key(&movement.livestock_id, |animal| animal.id)
+                .is_ok()
+        });
+
+        // finding who's on duty looks back across midnight, so the shifts start a day early
+        stream.write("shift", self.shifts.take_unwritten());
+        let payroll_start = first_day_of_month(as_of.date().pred()).and_hms(0, 0, 0);
+        self.shifts
+            .drop_unless(|shift| shift.start >= payroll_start);
+        stream.write("payroll", self.payrolls.take_unwritten());
+        self.payrolls
+            .drop_unless(|payroll| payroll.month.year() >= as_of.year());
+
+        stream.write(
+            "production_report",
+            &self.production_reports.drop_unless(|_| false),
+        );
+        stream.write("sale", &self.sales.drop_unless(|_| false));
+        stream.write("sale_line", &self.sale_lines.drop_unless(|_| false));
+        stream.write("treatment", &self.treatments.drop_unless(|_| false));
+        stream.write(
+            "stock_movement",
+            &self.stock_movements.drop_unless(|_| false),
+        );
+        stream.write(
+            "purchase_order",
+            &self.purchase_orders.drop_unless(|_| false),
+        );
+        stream.write(
+            "purchase_order_line",
+            &self.purchase_order_lines.drop_unless(|_| false),
+        );
+    }
+
+    // Ends the stream with the animals still alive, the only rows it holds back until the end
+    pub fn finish_stream(&mut self, stream: &mut ReportStream) {
+        stream.write("livestock", &self.livestock.drop_unless(|_| false));
+    }
+
+    // The facts other than the reports, which a stream writes out as they're generated
+    fn save_fact_tables_to_dir(&self, dir: &str) {
+        let dir = Path::new(dir);
+        // every table is written by its own thread
+        thread::scope(|scope| {
+            scope.spawn(|| {
+                save_to_file(dir.join("livestock").with_extension("csv"), &self.livestock)
+            });
+            scope.spawn(|| save_to_file(dir.join("shift").with_extension("csv"), &self.shifts));
+            scope.spawn(|| save_to_file(dir.join("payroll").with_extension("csv"), &self.payrolls));
+            scope.spawn(|| {
+                save_to_file(
+                    dir.join("stock_movement").with_extension("csv"),
+                    &self.stock_movements,
+                )
+            });
+            scope.spawn(|| {
+                save_to_file(
+                    dir.join("production_report").with_extension("csv"),
+                    &self.production_reports,
+                )
+            });
+            scope.spawn(|| save_to_file(dir.join("sale").with_extension("csv"), &self.sales));
+            scope.spawn(|| {
+                save_to_file(
+                    dir.join("sale_line").with_extension("csv"),
+                    &self.sale_lines,
+                )
+            });
+            scope.spawn(|| {
+                save_to_file(
+                    dir.join("purchase_order").with_extension("csv"),
+                    &self.purchase_orders,
+                )
+            });
+            scope.spawn(|| {
+                save_to_file(
+                    dir.join("purchase_order_line").with_extension("csv"),
+                    &self.purchase_order_lines,
+                )
+            });
+            scope.spawn(|| {
+                save_to_file(
+                    dir.join("treatment").with_extension("csv"),
+                    &self.treatments,
+                )
+            });
+            scope.spawn(|| {
+                save_to_file(
+                    dir.join("livestock_movement").with_extension("csv"),
+                    &self.livestock_movements,
+                )
+            });
+        });
+    }
+
+    // The tables which are held in memory whole, so a stream writes them out at the end
+    pub fn save_held_tables_to_dir(&self, dir: &str) {
+        let dir = Path::new(dir);
+        // every table is written by its own thread
+        thread::scope(|scope| {
+            scope.spawn(|| save_to_file(dir.join("pasture").with_extension("csv"), &self.pastures));
+            scope.spawn(|| save_to_file(dir.join("species").with_extension("csv"), &self.species));
+            scope.spawn(|| save_to_file(dir.join("herd").with_extension("csv"), &self.herds));
+            scope.spawn(|| {
+                save_to_file(dir.join("employee").with_extension("csv"), &self.employees)
+            });
+            scope.spawn(|| {
+                save_to_file(
+                    dir.join("warehouse").with_extension("csv"),
+                    &self.warehouses,
+                )
+            });
+            scope.spawn(|| save_to_file(dir.join("product").with_extension("csv"), &self.products));
+            scope.spawn(|| save_to_file(dir.join("field").with_extension("csv"), &self.fields));
+            scope.spawn(|| save_to_file(dir.join("crop").with_extension("csv"), &self.crops));
+            scope.spawn(|| save_to_file(dir.join("harvest").with_extension("csv"), &self.harvests));
+            scope.spawn(|| {
+                save_to_file(dir.join("customer").with_extension("csv"), &self.customers)
+            });
+            scope.spawn(|| {
+                save_to_file(dir.join("supplier").with_extension("csv"), &self.suppliers)
+            });
+            scope.spawn(|| save_to_file(dir.join("weather").with_extension("csv"), &self.weather));
+            scope.spawn(|| {
+                save_to_file(dir.join("dim_date").with_extension("csv"), &self.dim_dates)
+            });
+            if !self.dim_times.is_empty() {
+                scope.spawn(|| {
+                    save_to_file(dir.join("dim_time").with_extension("csv"), &self.dim_times)
+                });
+            }
+        });
+    }
+
+    pub fn save_aggregates_to_dir(&self, dir: &str) {
+        let dir = Path::new(dir);
+        let aggregates = Aggregates::new(self);
+        save_with_headers_to_file(
+            dir.join("fill_by_pasture_kind_month").with_extension("csv"),
+            &aggregates.fill_by_pasture_kind_month,
+        );
+        save_with_headers_to_file(
+            dir.join("healthy_ratio_by_species_quarter")
+                .with_extension("csv"),
+            &aggregates.healthy_ratio_by_species_quarter,
+        );
+        save_with_headers_to_file(
+            dir.join("disposals_by_purpose_year").with_extension("csv"),
+            &aggregates.disposals_by_purpose_year,
+        );
+        save_with_headers_to_file(
+            dir.join("headcount_by_herd_week").with_extension("csv"),
+            &aggregates.headcount_by_herd_week,
+        );
+    }
+
+    // The star schema the OLTP tables saved by save_to_dir are expected to transform into
+    pub fn save_star_schema_to_dir(&self, dir: &str) {
         let dir = Path::new(dir);
-        save_to_file(dir.join("pasture").with_extension("csv"), &self.pastures);
-        save_to_file(dir.join("species").with_extension("csv"), &self.species);
-        save_to_file(dir.join("herd").with_extension("csv"), &self.herds);
+        let star_schema = StarSchema::new(self);
         save_to_file(
-            dir.join("feeding_report").with_extension("csv"),
-            &self.feeding_reports,
+            dir.join("dim_species").with_extension("csv"),
+            &star_schema.dim_species,
         );
-        save_to_file(dir.join("livestock").with_extension("csv"), &self.livestock);
-        save_to_file(dir.join("employee").with_extension("csv"), &self.employees);
         save_to_file(
-            dir.join("health_report").with_extension("csv"),
-            &self.health_reports,
+            dir.join("dim_pasture").with_extension("csv"),
+            &star_schema.dim_pastures,
+        );
+        save_to_file(
+            dir.join("dim_herd").with_extension("csv"),
+            &star_schema.dim_herds,
+        );
+        save_to_file(
+            dir.join("dim_employee").with_extension("csv"),
+            &star_schema.dim_employees,
         );
+        save_to_file(dir.join("dim_date").with_extension("csv"), &self.dim_dates);
         save_to_file(
-            dir.join("warehouse").with_extension("csv"),
-            &self.warehouses,
+            dir.join("fact_headcount").with_extension("csv"),
+            &star_schema.fact_headcounts,
         );
         save_to_file(
-            dir.join("headcount_report").with_extension("csv"),
-            &self.headcount_reports,
+            dir.join("fact_health").with_extension("csv"),
+            &star_schema.fact_healths,
+        );
+        save_to_file(
+            dir.join("fact_feeding").with_extension("csv"),
+            &star_schema.fact_feedings,
+        );
+        save_to_file(
+            dir.join("fact_livestock_disposal").with_extension("csv"),
+            &star_schema.fact_livestock_disposals,
         );
     }
 }
 
-fn save_to_file<P, T>(path: P, data: &Vec<T>)
+fn count_rows<P: AsRef<Path>>(path: P) -> Result<usize, String> {
+    let path = path.as_ref();
+    csv::ReaderBuilder::new()
+        .has_headers(false)
+        .from_path(path)
+        .and_then(|mut reader| reader.records().collect::<Result<Vec<_>, _>>())
+        .map(|records| records.len())
+        .map_err(|error| format!("{}: {}", path.display(), error))
+}
+
+// The files have no headers, so the columns are read in the order of the fields
+fn load_from_file<P, T>(path: P) -> Result<Vec<T>, String>
+where
+    P: AsRef<Path>,
+    T: DeserializeOwned,
+{
+    let path = path.as_ref();
+    csv::ReaderBuilder::new()
+        .has_headers(false)
+        .from_path(path)
+        .and_then(|mut reader| reader.deserialize().collect())
+        .map_err(|error| format!("{}: {}", path.display(), error))
+}
+
+fn save_to_file<P, T>(path: P, data: &[T])
+where
+    P: Deref<Target = Path> + AsRef<Path>,
+    T: Serialize,
+{
+    write_to_file(path, data, false);
+}
+
+// Unlike the tables, the query results aren't part of the model, so their columns are named in the
+// first row
+fn save_with_headers_to_file<P, T>(path: P, data: &[T])
+where
+    P: Deref<Target = Path> + AsRef<Path>,
+    T: Serialize,
+{
+    write_to_file(path, data, true);
+}
+
+fn write_to_file<P, T>(path: P, data: &[T], has_headers: bool)
 where
     P: Deref<Target = Path> + AsRef<Path>,
     T: Serialize,
@@ -153,7 +823,7 @@ where
         .open(path)
         .unwrap();
     let mut writer = csv::WriterBuilder::new()
-        .has_headers(false)
+        .has_headers(has_headers)
         .from_writer(file);
     for elem in data.iter() {
         writer.serialize(elem).unwrap();
@@ -5,7 +5,7 @@ use serde::Serialize;
 
 use crate::pasture::PastureKind;
 
-#[derive(Serialize)]
+#[derive(Clone, Copy, Serialize)]
 pub enum SpeciesKind {
     Animal,
     Plant,
@@ -15,16 +15,23 @@ pub struct SpeciesAreaRequirements {
     pub pasture_kind_to_req_area: EnumMap<PastureKind, f32>,
 }
 
+pub struct CropYield {
+    pub min_per_area: f32, // in kilograms per square meter
+    pub max_per_area: f32,
+}
+
 #[derive(Serialize)]
 pub struct Species<'a> {
     pub id: usize,
-    name: &'a str,
-    kind: SpeciesKind,
+    pub name: &'a str,
+    pub kind: SpeciesKind,
 
     #[serde(skip_serializing)]
     pub lifespan: Duration,
     #[serde(skip_serializing)]
     pub area_requirements: Option<SpeciesAreaRequirements>,
+    #[serde(skip_serializing)]
+    pub crop_yield: Option<CropYield>,
 }
 
 impl<'a> Species<'a> {
@@ -34,6 +41,7 @@ impl<'a> Species<'a> {
         kind: SpeciesKind,
         lifespan_days: u64,
         area_requirements: Option<SpeciesAreaRequirements>,
+        crop_yield: Option<CropYield>,
     ) -> Self {
         Species {
             id,
@@ -41,6 +49,7 @@ impl<'a> Species<'a> {
             kind,
             lifespan: Duration::from_secs(lifespan_days * 24 * 60 * 60),
             area_requirements,
+            crop_yield,
         }
     }
 }
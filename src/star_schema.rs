@@ -0,0 +1,305 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::Serialize;
+
+use crate::{
+    calendar::date_key,
+    employee::{Employee, EmployeeRole, Sex},
+    feeding_report::FeedingReport,
+    headcount_report::HeadcountReport,
+    health_report::HealthReport,
+    herd::Herd,
+    late_arrival::arrived_by,
+    livestock::{DisposalPurpose, Livestock},
+    livestock_movement::HerdMembership,
+    pasture::{Pasture, PastureKind},
+    snapshot::Snapshot,
+    species::{Species, SpeciesKind},
+};
+
+// Surrogate keys start at 1 and follow the order of the source rows, so the keys of a dimension
+// row stay the same in every later snapshot
+const fn surrogate_key(id: usize) -> usize {
+    id + 1
+}
+
+#[derive(Serialize)]
+pub struct DimSpecies<'a> {
+    species_key: usize,
+    species_id: usize,
+    name: &'a str,
+    kind: SpeciesKind,
+}
+
+impl<'a> DimSpecies<'a> {
+    pub const fn new(species: &Species<'a>) -> Self {
+        DimSpecies {
+            species_key: surrogate_key(species.id),
+            species_id: species.id,
+            name: species.name,
+            kind: species.kind,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct DimPasture {
+    pasture_key: usize,
+    pasture_id: usize,
+    kind: PastureKind,
+    area: f32,
+    feeder_capacity: f32, // in kilograms of feed
+}
+
+impl DimPasture {
+    pub fn new(pasture: &Pasture) -> Self {
+        DimPasture {
+            pasture_key: surrogate_key(pasture.id),
+            pasture_id: pasture.id,
+            kind: pasture.kind,
+            area: pasture.area,
+            feeder_capacity: pasture.feeder_capacity(),
+        }
+    }
+}
+
+// Denormalized with the attributes of the herd's pasture and species
+#[derive(Serialize)]
+pub struct DimHerd<'a> {
+    herd_key: usize,
+    herd_id: usize,
+    pasture_id: usize,
+    pasture_kind: PastureKind,
+    species_id: usize,
+    species_name: &'a str,
+    area: f32,
+    capacity: usize,
+}
+
+impl<'a> DimHerd<'a> {
+    pub const fn new(herd: &Herd, pasture: &Pasture, species: &Species<'a>) -> Self {
+        DimHerd {
+            herd_key: surrogate_key(herd.id),
+            herd_id: herd.id,
+            pasture_id: pasture.id,
+            pasture_kind: pasture.kind,
+            species_id: species.id,
+            species_name: species.name,
+            area: herd.area,
+            capacity: herd.capacity,
+        }
+    }
+}
+
+// Without the PESEL, birth date, account number and salary, which have no place in the warehouse;
+// the name stays, so that whoever reads the reports can tell who filed them
+#[derive(Serialize)]
+pub struct DimEmployee<'a> {
+    employee_key: usize,
+    employee_id: usize,
+    name: &'a str,
+    surname: &'a str,
+    sex: Sex,
+    role: EmployeeRole,
+    hire_date: NaiveDate,
+    termination_date: Option<NaiveDate>,
+}
+
+impl<'a> DimEmployee<'a> {
+    pub fn new(employee: &'a Employee) -> Self {
+        DimEmployee {
+            employee_key: surrogate_key(employee.id),
+            employee_id: employee.id,
+            name: &employee.name,
+            surname: &employee.surname,
+            sex: employee.sex,
+            role: employee.role,
+            hire_date: employee.hire_date,
+            termination_date: employee.termination_date,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct FactHeadcount {
+    date_key: u32,
+    herd_key: usize,
+    pasture_key: usize,
+    species_key: usize,
+    employee_key: usize,
+    headcount_report_id: usize, // degenerate dimension
+    timestamp: NaiveDateTime,
+    quantity: u32,
+    capacity: usize,
+    overstocked: bool,
+}
+
+impl FactHeadcount {
+    pub fn new(report: &HeadcountReport, herd: &Herd) -> Self {
+        FactHeadcount {
+            date_key: date_key(report.timestamp.date()),
+            herd_key: surrogate_key(herd.id),
+            pasture_key: surrogate_key(herd.pasture_id),
+            species_key: surrogate_key(herd.species_id),
+            employee_key: surrogate_key(report.employee_id),
+            headcount_report_id: report.id,
+            timestamp: report.timestamp,
+            quantity: report.quantity,
+            capacity: herd.capacity,
+            overstocked: report.overstocked,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct FactHealth {
+    date_key: u32,
+    herd_key: usize,
+    pasture_key: usize,
+    species_key: usize,
+    employee_key: usize,
+    health_report_id: usize,
+    timestamp: NaiveDateTime,
+    healthy_count: u32,
+    ill_count: u32,
+    severly_ill_count: u32,
+    terminal_count: u32,
+    total_count: u32,
+    healthy_ratio: f32,
+}
+
+impl FactHealth {
+    pub fn new(report: &HealthReport, herd: &Herd) -> Self {
+        FactHealth {
+            date_key: date_key(report.timestamp.date()),
+            herd_key: surrogate_key(herd.id),
+            pasture_key: surrogate_key(herd.pasture_id),
+            species_key: surrogate_key(herd.species_id),
+            employee_key: surrogate_key(report.employee_id),
+            health_report_id: report.id,
+            timestamp: report.timestamp,
+            healthy_count: report.healthy_count,
+            ill_count: report.ill_count,
+            severly_ill_count: report.severly_ill_count,
+            terminal_count: report.terminal_count,
+            total_count: report.total_count(),
+            healthy_ratio: report.healthy_ratio(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct FactFeeding {
+    date_key: u32,
+    pasture_key: usize,
+    feeding_report_id: usize,
+    start_fill_pct: f32,
+    end_fill_pct: f32,
+    refilled_feed: f32, // in kilograms
+}
+
+impl FactFeeding {
+    pub fn new(report: &FeedingReport, pasture: &Pasture) -> Self {
+        FactFeeding {
+            date_key: date_key(report.date),
+            pasture_key: surrogate_key(pasture.id),
+            feeding_report_id: report.id,
+            start_fill_pct: report.start_fill_pct,
+            end_fill_pct: report.end_fill_pct,
+            refilled_feed: (report.end_fill_pct - report.start_fill_pct) / 100.
+                * pasture.feeder_capacity(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct FactLivestockDisposal {
+    date_key: u32,
+    herd_key: usize, // the herd the animal belonged to when it was disposed of
+    pasture_key: usize,
+    species_key: usize,
+    livestock_id: usize,
+    disposal: NaiveDateTime,
+    disposal_purpose: DisposalPurpose,
+    age_days: i64,
+    disposal_weight: Option<f32>,
+    disposal_price: Option<f32>,
+    disposal_destination: Option<&'static str>,
+}
+
+impl FactLivestockDisposal {
+    // Animals which haven't been disposed of yet have no fact
+    pub fn new(animal: &Livestock, membership: &HerdMembership, herds: &[Herd]) -> Option<Self> {
+        let (disposal, disposal_purpose) = match (animal.disposal, animal.disposal_purpose) {
+            (Some(disposal), Some(purpose)) => (disposal, purpose),
+            _ => return None,
+        };
+        let herd = &herds[membership.herd_at(animal, disposal)];
+        Some(FactLivestockDisposal {
+            date_key: date_key(disposal.date()),
+            herd_key: surrogate_key(herd.id),
+            pasture_key: surrogate_key(herd.pasture_id),
+            species_key: surrogate_key(animal.species_id),
+            livestock_id: animal.id,
+            disposal,
+            disposal_purpose,
+            age_days: disposal.signed_duration_since(animal.birth).num_days(),
+            disposal_weight: animal.disposal_weight,
+            disposal_price: animal.disposal_price,
+            disposal_destination: animal.disposal_destination,
+        })
+    }
+}
+
+// The reference result of transforming the OLTP tables of a snapshot into a star schema; the facts
+// only cover the reports which arrived by the time of the snapshot, like the OLTP tables do
+pub struct StarSchema<'a> {
+    pub dim_species: Vec<DimSpecies<'a>>,
+    pub dim_pastures: Vec<DimPasture>,
+    pub dim_herds: Vec<DimHerd<'a>>,
+    pub dim_employees: Vec<DimEmployee<'a>>,
+    pub fact_headcounts: Vec<FactHeadcount>,
+    pub fact_healths: Vec<FactHealth>,
+    pub fact_feedings: Vec<FactFeeding>,
+    pub fact_livestock_disposals: Vec<FactLivestockDisposal>,
+}
+
+impl<'a> StarSchema<'a> {
+    pub fn new(snapshot: &'a Snapshot) -> Self {
+        let herds = &snapshot.herds;
+        let pastures = &snapshot.pastures;
+        let membership =
+            HerdMembership::new(&snapshot.livestock, &snapshot.livestock_movements, herds);
+        StarSchema {
+            dim_species: snapshot.species.iter().map(DimSpecies::new).collect(),
+            dim_pastures: pastures.iter().map(DimPasture::new).collect(),
+            dim_herds: herds
+                .iter()
+                .map(|herd| {
+                    DimHerd::new(
+                        herd,
+                        &pastures[herd.pasture_id],
+                        &snapshot.species[herd.species_id],
+                    )
+                })
+                .collect(),
+            dim_employees: snapshot.employees.iter().map(DimEmployee::new).collect(),
+            fact_headcounts: arrived_by(&snapshot.headcount_reports, snapshot.as_of)
+                .into_iter()
+                .map(|report| FactHeadcount::new(report, &herds[report.herd_id]))
+                .collect(),
+            fact_healths: arrived_by(&snapshot.health_reports, snapshot.as_of)
+                .into_iter()
+                .map(|report| FactHealth::new(report, &herds[report.herd_id]))
+                .collect(),
+            fact_feedings: arrived_by(&snapshot.feeding_reports, snapshot.as_of)
+                .into_iter()
+                .map(|report| FactFeeding::new(report, &pastures[report.pasture_id]))
+                .collect(),
+            fact_livestock_disposals: snapshot
+                .livestock
+                .iter()
+                .filter_map(|animal| FactLivestockDisposal::new(animal, &membership, herds))
+                .collect(),
+        }
+    }
+}
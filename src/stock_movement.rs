@@ -0,0 +1,712 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use chrono::{Datelike, Duration, NaiveDateTime, Weekday};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    crop::Harvest,
+    feeding_report::FeedingReport,
+    herd::Herd,
+    pasture::Pasture,
+    product::{product_for_species, Product, ProductKind},
+    purchase_order::{PendingDelivery, Purchasing},
+    snapshot::{Expansion, FarmConfig},
+    table::Table,
+    treatment::Treatment,
+    warehouse::Warehouse,
+};
+
+const MIN_MOVEMENT_QUANTITY: f32 = 0.01;
+const DELIVERY_HOUR: u32 = 10;
+const REORDER_MARGIN: f64 = 1.5; // of the demand over the time an order can take to arrive
+const CROP_SALE_WEEKDAY: Weekday = Weekday::Mon;
+const CROP_SALE_HOUR: u32 = 8;
+const CROP_SALE_PCT: f32 = 0.1; // of the stock in every warehouse, every week
+const CROP_SALE_MIN_LOT: f32 = 1_000.; // in kilograms
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum MovementKind {
+    Delivery,
+    Withdrawal,
+}
+
+// Where the stock comes from or goes to
+#[derive(Clone, Copy)]
+pub enum MovementSource<'a> {
+    PurchaseOrderLine(&'a PendingDelivery),
+    FeedingReport(&'a FeedingReport),
+    Harvest(&'a Harvest),
+    Treatment(&'a Treatment),
+    Sale,
+    OpeningStock,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct StockMovement {
+    pub id: usize,
+    pub warehouse_id: usize,
+    pub product_id: usize,
+    pub timestamp: NaiveDateTime,
+    pub kind: MovementKind,
+    pub quantity: f32,
+    pasture_id: Option<usize>,
+    feeding_report_id: Option<usize>,
+    harvest_id: Option<usize>,
+    purchase_order_line_id: Option<usize>,
+    treatment_id: Option<usize>,
+}
+
+impl StockMovement {
+    pub const fn new(
+        id: usize,
+        warehouse: &Warehouse,
+        product: &Product,
+        timestamp: NaiveDateTime,
+        kind: MovementKind,
+        quantity: f32,
+        source: MovementSource,
+    ) -> Self {
+        let (pasture_id, feeding_report_id, harvest_id, purchase_order_line_id, treatment_id) =
+            match source {
+                MovementSource::PurchaseOrderLine(delivery) => {
+                    (None, None, None, Some(delivery.line_id), None)
+                }
+                MovementSource::FeedingReport(report) => {
+                    (Some(report.pasture_id), Some(report.id), None, None, None)
+                }
+                MovementSource::Harvest(harvest) => (None, None, Some(harvest.id), None, None),
+                MovementSource::Treatment(treatment) => {
+                    (None, None, None, None, Some(treatment.id))
+                }
+                MovementSource::Sale | MovementSource::OpeningStock => {
+                    (None, None, None, None, None)
+                }
+            };
+        StockMovement {
+            id,
+            warehouse_id: warehouse.id,
+            product_id: product.id,
+            timestamp,
+            kind,
+            quantity,
+            pasture_id,
+            feeding_report_id,
+            harvest_id,
+            purchase_order_line_id,
+            treatment_id,
+        }
+    }
+
+    // Withdrawals which aren't used on the farm are sold
+    pub const fn is_sale(&self) -> bool {
+        matches!(self.kind, MovementKind::Withdrawal)
+            && self.pasture_id.is_none()
+            && self.feeding_report_id.is_none()
+            && self.harvest_id.is_none()
+            && self.purchase_order_line_id.is_none()
+            && self.treatment_id.is_none()
+    }
+}
+
+// Quantity of every product in every warehouse, as a result of all the movements so far
+pub struct StockLevels {
+    quantities: HashMap<(usize, usize), f64>,
+    used_volumes: Vec<f64>,
+}
+
+impl StockLevels {
+    pub fn new(
+        movements: &[StockMovement],
+        warehouses: &[Warehouse],
+        products: &[Product],
+    ) -> Self {
+        let mut levels = StockLevels {
+            quantities: HashMap::new(),
+            used_volumes: vec![0.; warehouses.len()],
+        };
+        for movement in movements {
+            levels.apply(movement, products);
+        }
+        levels
+    }
+
+    pub fn quantity(&self, warehouse_id: usize, product_id: usize) -> f32 {
+        *self
+            .quantities
+            .get(&(warehouse_id, product_id))
+            .unwrap_or(&0.) as f32
+    }
+
+    pub fn free_volume(&self, warehouse: &Warehouse) -> f32 {
+        (warehouse.volume - self.used_volumes[warehouse.id] as f32).max(0.)
+    }
+
+    pub fn apply(&mut self, movement: &StockMovement, products: &[Product]) {
+        let signed_quantity = match movement.kind {
+            MovementKind::Delivery => movement.quantity as f64,
+            MovementKind::Withdrawal => -movement.quantity as f64,
+        };
+        *self
+            .quantities
+            .entry((movement.warehouse_id, movement.product_id))
+            .or_insert(0.) += signed_quantity;
+        self.used_volumes[movement.warehouse_id] +=
+            signed_quantity * products[movement.product_id].volume_per_unit as f64;
+    }
+}
+
+// Where the stock stands between expansions, kept with the snapshot so that the movements don't
+// have to be replayed, or even held in memory, to carry on
+pub struct StockState {
+    levels: StockLevels,
+    pending: Vec<PendingDelivery>, // the latest to arrive first
+    on_order: HashMap<(usize, usize), f32>,
+    demand: HashMap<(usize, usize), Demand>,
+    opened_warehouse_count: usize, // the ones built later haven't had their opening stock yet
+}
+
+impl StockState {
+    // Worked out from the movements so far; the orders placed before the given moment which are
+    // due after it are still on their way
+    pub fn new(
+        movements: &[StockMovement],
+        purchasing: &Purchasing,
+        products: &[Product],
+        warehouses: &[Warehouse],
+        from_when: NaiveDateTime,
+    ) -> Self {
+        let levels = StockLevels::new(movements, warehouses, products);
+        let lead_time = purchasing.max_delivery_delay();
+        let mut delivered_line_ids = HashSet::new();
+        // the earlier withdrawals are attributed to the warehouses they were taken from rather
+        // than the ones they were meant for, which is close enough for a fresh estimate
+        let mut demand: HashMap<(usize, usize), Demand> = HashMap::new();
+        for movement in movements {
+            if let Some(line_id) = movement.purchase_order_line_id {
+                delivered_line_ids.insert(line_id);
+            }
+            if movement.kind == MovementKind::Withdrawal && !movement.is_sale() {
+                demand
+                    .entry((movement.warehouse_id, movement.product_id))
+                    .or_default()
+                    .record(movement.timestamp, movement.quantity, lead_time);
+            }
+        }
+
+        let mut state = StockState {
+            levels,
+            pending: vec![],
+            on_order: HashMap::new(),
+            demand,
+            // a farm with any stock has had its warehouses opened already
+            opened_warehouse_count: if movements.is_empty() {
+                0
+            } else {
+                warehouses.len()
+            },
+        };
+        for delivery in purchasing.pending_deliveries(&delivered_line_ids) {
+            if delivery.date >= from_when.date() {
+                state.expect(delivery);
+            }
+        }
+        state
+    }
+
+    fn expect(&mut self, delivery: PendingDelivery) {
+        *self
+            .on_order
+            .entry((delivery.warehouse_id, delivery.product_id))
+            .or_insert(0.) += delivery.quantity;
+        let idx = self
+            .pending
+            .partition_point(|pending| pending.date > delivery.date);
+        self.pending.insert(idx, delivery);
+    }
+}
+
+// What was withdrawn for a warehouse over the time an order can take to arrive
+#[derive(Default)]
+struct Demand {
+    withdrawals: VecDeque<(NaiveDateTime, f32)>,
+    total: f64,
+}
+
+impl Demand {
+    fn record(&mut self, timestamp: NaiveDateTime, quantity: f32, lead_time: Duration) {
+        self.withdrawals.push_back((timestamp, quantity));
+        self.total += quantity as f64;
+        while let Some(&(oldest, quantity)) = self.withdrawals.front() {
+            if oldest > timestamp - lead_time {
+                break;
+            }
+            self.withdrawals.pop_front();
+            self.total -= quantity as f64;
+        }
+    }
+}
+
+// The warehouses along with their stock, and the purchasing department keeping it up
+pub struct Stockroom<'a> {
+    movements: &'a mut Table<StockMovement>,
+    state: &'a mut StockState,
+    purchasing: Purchasing<'a>,
+    products: &'a [Product<'a>],
+    warehouses: &'a [Warehouse],
+    lead_time: Duration,
+}
+
+impl<'a> Stockroom<'a> {
+    pub fn new(
+        movements: &'a mut Table<StockMovement>,
+        state: &'a mut StockState,
+        purchasing: Purchasing<'a>,
+        products: &'a [Product<'a>],
+        warehouses: &'a [Warehouse],
+    ) -> Self {
+        // warehouses built since start out empty
+        state.levels.used_volumes.resize(warehouses.len(), 0.);
+        let lead_time = purchasing.max_delivery_delay();
+        Stockroom {
+            movements,
+            state,
+            purchasing,
+            products,
+            warehouses,
+            lead_time,
+        }
+    }
+
+    // Records the movement and updates the levels accordingly
+    fn push(&mut self, movement: StockMovement) {
+        self.state.levels.apply(&movement, self.products);
+        self.movements.push(movement);
+    }
+
+    // Every warehouse serves its share of the pastures and fields, which are spread over them in
+    // turn
+    fn serving_warehouse(&self, id: usize) -> &'a Warehouse {
+        &self.warehouses[id % self.warehouses.len()]
+    }
+
+    fn on_order(&self, warehouse: &Warehouse, product: &Product) -> f32 {
+        *self
+            .state
+            .on_order
+            .get(&(warehouse.id, product.id))
+            .unwrap_or(&0.)
+    }
+
+    // The free volume, less what the deliveries on their way will take up
+    fn room(&self, warehouse: &Warehouse, product: &Product) -> f32 {
+        let volume_on_order: f32 = self
+            .state
+            .pending
+            .iter()
+            .filter(|delivery| delivery.warehouse_id == warehouse.id)
+            .map(|delivery| delivery.quantity * self.products[delivery.product_id].volume_per_unit)
+            .sum();
+        (self.state.levels.free_volume(warehouse) - volume_on_order).max(0.)
+            / product.volume_per_unit
+    }
+
+    // Unloads the deliveries which arrived by the given moment
+    fn receive(&mut self, until: NaiveDateTime) {
+        while let Some(delivery) = self.state.pending.last() {
+            let timestamp = delivery.date.and_hms(DELIVERY_HOUR, 0, 0);
+            if timestamp > until {
+                break;
+            }
+            let delivery = self.state.pending.pop().unwrap();
+            *self
+                .state
+                .on_order
+                .get_mut(&(delivery.warehouse_id, delivery.product_id))
+                .unwrap() -= delivery.quantity;
+            let warehouse = &self.warehouses[delivery.warehouse_id];
+            let product = &self.products[delivery.product_id];
+            self.store(
+                timestamp,
+                delivery.quantity,
+                product,
+                warehouse,
+                MovementSource::PurchaseOrderLine(&delivery),
+            );
+        }
+    }
+
+    // The quantity is withdrawn from the warehouse serving where it's needed, or when it ran out,
+    // from the one with the most of the product; returns what couldn't be withdrawn
+    fn withdraw(
+        &mut self,
+        timestamp: NaiveDateTime,
+        quantity: f32,
+        product: &Product,
+        serving: &Warehouse,
+        source: MovementSource,
+        delivery_quantity: f32,
+    ) -> f32 {
+        self.state
+            .demand
+            .entry((serving.id, product.id))
+            .or_default()
+            .record(timestamp, quantity, self.lead_time);
+        let mut remaining = quantity;
+
+        while remaining >= MIN_MOVEMENT_QUANTITY {
+            let warehouse =
+                if self.state.levels.quantity(serving.id, product.id) >= MIN_MOVEMENT_QUANTITY {
+                    serving
+                } else {
+                    fullest_warehouse(&self.state.levels, self.warehouses, product)
+                };
+            let available = self.state.levels.quantity(warehouse.id, product.id);
+            if available < MIN_MOVEMENT_QUANTITY {
+                break;
+            }
+            let quantity = remaining.min(available);
+            let withdrawal = StockMovement::new(
+                self.movements.next_id(),
+                warehouse,
+                product,
+                timestamp,
+                MovementKind::Withdrawal,
+                quantity,
+                source,
+            );
+            self.push(withdrawal);
+            remaining -= quantity;
+        }
+        self.reorder(timestamp, product, serving, delivery_quantity);
+        if remaining >= MIN_MOVEMENT_QUANTITY {
+            remaining
+        } else {
+            0.
+        }
+    }
+
+    // Once the stock along with what's on its way runs below what's withdrawn over the time an
+    // order can take to arrive, with a margin, more is ordered for the serving warehouse; when it
+    // has no room, the stock of all the warehouses counts, and the order goes to the one with the
+    // most room
+    fn reorder(
+        &mut self,
+        timestamp: NaiveDateTime,
+        product: &Product,
+        serving: &Warehouse,
+        delivery_quantity: f32,
+    ) {
+        let demand = self
+            .state
+            .demand
+            .get(&(serving.id, product.id))
+            .map_or(0., |demand| demand.total);
+        let reorder_point = (demand * REORDER_MARGIN) as f32;
+        let serving_has_room = self.room(serving, product) >= MIN_MOVEMENT_QUANTITY;
+        let stocked_in = if serving_has_room {
+            std::slice::from_ref(serving)
+        } else {
+            self.warehouses
+        };
+        let position: f32 = stocked_in
+            .iter()
+            .map(|warehouse| {
+                self.state.levels.quantity(warehouse.id, product.id)
+                    + self.on_order(warehouse, product)
+            })
+            .sum();
+        if position >= reorder_point.max(MIN_MOVEMENT_QUANTITY) {
+            return;
+        }
+
+        let warehouse = if serving_has_room {
+            serving
+        } else {
+            self.warehouses
+                .iter()
+                .max_by(|a, b| {
+                    self.room(a, product)
+                        .partial_cmp(&self.room(b, product))
+                        .unwrap()
+                })
+                .unwrap()
+        };
+        let quantity = delivery_quantity
+            .max(reorder_point - position)
+            .min(self.room(warehouse, product));
+        if quantity < MIN_MOVEMENT_QUANTITY {
+            return;
+        }
+        if let Some(delivery) =
+            self.purchasing
+                .order(product, warehouse, quantity, timestamp.date())
+        {
+            self.state.expect(delivery);
+        }
+    }
+
+    // The quantity goes to the warehouse serving where it comes from, and what doesn't fit there
+    // to the ones with the most room; whatever doesn't fit in any is turned away
+    fn store(
+        &mut self,
+        timestamp: NaiveDateTime,
+        quantity: f32,
+        product: &Product,
+        serving: &Warehouse,
+        source: MovementSource,
+    ) {
+        let mut remaining = quantity;
+
+        while remaining >= MIN_MOVEMENT_QUANTITY {
+            let warehouse = if self.state.levels.free_volume(serving) / product.volume_per_unit
+                >= MIN_MOVEMENT_QUANTITY
+            {
+                serving
+            } else {
+                roomiest_warehouse(&self.state.levels, self.warehouses)
+            };
+            let quantity =
+                remaining.min(self.state.levels.free_volume(warehouse) / product.volume_per_unit);
+            if quantity < MIN_MOVEMENT_QUANTITY {
+                break;
+            }
+            let delivery = StockMovement::new(
+                self.movements.next_id(),
+                warehouse,
+                product,
+                timestamp,
+                MovementKind::Delivery,
+                quantity,
+                source,
+            );
+            self.push(delivery);
+            remaining -= quantity;
+        }
+    }
+
+    // The warehouses built since open with a delivery of every feed and medicine, as much as
+    // usually comes in one, which isn't ordered from any of the suppliers
+    fn open_new_warehouses(&mut self, timestamp: NaiveDateTime, config: &FarmConfig) {
+        for warehouse in &self.warehouses[self.state.opened_warehouse_count..] {
+            for product in self.products {
+                let quantity = match product.kind {
+                    ProductKind::Feed => config.feed_delivery_quantity,
+                    ProductKind::Medicine => config.medicine_delivery_quantity,
+                    _ => continue,
+                }
+                .min(self.room(warehouse, product));
+                if quantity < MIN_MOVEMENT_QUANTITY {
+                    continue;
+                }
+                let delivery = StockMovement::new(
+                    self.movements.next_id(),
+                    warehouse,
+                    product,
+                    timestamp,
+                    MovementKind::Delivery,
+                    quantity,
+                    MovementSource::OpeningStock,
+                );
+                self.push(delivery);
+            }
+        }
+        self.state.opened_warehouse_count = self.warehouses.len();
+    }
+
+    // Harvests which don't fit in any warehouse are as good as sold straight off the field
+    fn store_harvest(&mut self, harvest: &Harvest, crop: &Product) {
+        let serving = self.serving_warehouse(harvest.field_id);
+        self.store(
+            harvest.date.and_hms(0, 0, 0),
+            harvest.quantity,
+            crop,
+            serving,
+            MovementSource::Harvest(harvest),
+        );
+    }
+
+    // Every warehouse sells a share of the crop it holds, but at least a full lot, so the stock
+    // runs out eventually instead of dwindling forever
+    fn sell_crop(&mut self, timestamp: NaiveDateTime, crop: &Product) {
+        for warehouse in self.warehouses {
+            let available = self.state.levels.quantity(warehouse.id, crop.id);
+            if available < MIN_MOVEMENT_QUANTITY {
+                continue;
+            }
+            let quantity = (available * CROP_SALE_PCT)
+                .max(CROP_SALE_MIN_LOT)
+                .min(available);
+            let withdrawal = StockMovement::new(
+                self.movements.next_id(),
+                warehouse,
+                crop,
+                timestamp,
+                MovementKind::Withdrawal,
+                quantity,
+                MovementSource::Sale,
+            );
+            self.push(withdrawal);
+        }
+    }
+}
+
+enum StockEvent {
+    Refill(usize),
+    Harvest(usize),
+    Treatment(usize),
+    CropSale,
+}
+
+// Replays everything that moves stock in or out of the warehouses in chronological order, so the
+// warehouses never hold more than they can fit
+pub fn expand_stock_movement_vec(
+    stockroom: &mut Stockroom,
+    feeding_reports: &mut [FeedingReport],
+    harvests: &[Harvest],
+    treatments: &[Treatment],
+    pastures: &[Pasture],
+    herds: &[Herd],
+    expansion: &Expansion,
+) {
+    let products = stockroom.products;
+    let config = expansion.config;
+    let mut pasture_herds: Vec<Vec<&Herd>> = pastures.iter().map(|_| vec![]).collect();
+    for herd in herds {
+        pasture_herds[herd.pasture_id].push(herd);
+    }
+    // the report following each one on the same pasture, which starts from what it left
+    let mut next_reports: Vec<Option<usize>> = vec![None; feeding_reports.len()];
+    let mut latest_reports: HashMap<usize, usize> = HashMap::new();
+    for (idx, report) in feeding_reports.iter().enumerate() {
+        if let Some(latest) = latest_reports.insert(report.pasture_id, idx) {
+            next_reports[latest] = Some(idx);
+        }
+    }
+
+    let mut events: Vec<(NaiveDateTime, StockEvent)> = feeding_reports
+        .iter()
+        .enumerate()
+        .map(|(idx, report)| (report.date.and_hms(0, 0, 0), StockEvent::Refill(idx)))
+        .chain(
+            harvests
+                .iter()
+                .enumerate()
+                .map(|(idx, harvest)| (harvest.date.and_hms(0, 0, 0), StockEvent::Harvest(idx))),
+        )
+        .chain(
+            treatments
+                .iter()
+                .enumerate()
+                .map(|(idx, treatment)| (treatment.start, StockEvent::Treatment(idx))),
+        )
+        .chain(
+            expansion
+                .first_day()
+                .iter_days()
+                .take_while(|day| *day < expansion.last_day())
+                .filter(|day| day.weekday() == CROP_SALE_WEEKDAY)
+                .map(|day| (day.and_hms(CROP_SALE_HOUR, 0, 0), StockEvent::CropSale)),
+        )
+        .collect();
+    events.sort_by_key(|(timestamp, _)| *timestamp);
+
+    stockroom.open_new_warehouses(expansion.from_when, config);
+    for (timestamp, event) in events {
+        stockroom.receive(timestamp);
+        match event {
+            StockEvent::Refill(idx) => {
+                let report = &mut feeding_reports[idx];
+                let pasture = &pastures[report.pasture_id];
+                let serving = stockroom.serving_warehouse(pasture.id);
+                let refill = pasture.feeder_capacity()
+                    * (report.end_fill_pct - report.start_fill_pct)
+                    / 100.;
+                // the herds sharing the pasture eat from the feeder in proportion to their area,
+                // each the feed for its species
+                let herds = &pasture_herds[pasture.id];
+                let herds_area: f32 = herds.iter().map(|herd| herd.area).sum();
+                let mut remaining = 0.;
+                for herd in herds {
+                    let feed = product_for_species(products, ProductKind::Feed, herd.species_id);
+                    if let Some(feed) = feed {
+                        remaining += stockroom.withdraw(
+                            timestamp,
+                            refill * herd.area / herds_area,
+                            feed,
+                            serving,
+                            MovementSource::FeedingReport(report),
+                            config.feed_delivery_quantity,
+                        );
+                    }
+                }
+                // the warehouses ran out, so the feeder isn't refilled completely, and the next
+                // report finds it that much emptier, the herds eating the same share of it
+                let end_fill_pct = report.end_fill_pct;
+                report.end_fill_pct -= remaining / pasture.feeder_capacity() * 100.;
+                if let Some(next) = next_reports[idx] {
+                    let left_ratio = match end_fill_pct {
+                        fill if fill > 0. => feeding_reports[next].start_fill_pct / fill,
+                        _ => 0.,
+                    };
+                    feeding_reports[next].start_fill_pct =
+                        feeding_reports[idx].end_fill_pct * left_ratio;
+                }
+            }
+            StockEvent::Harvest(idx) => {
+                let harvest = &harvests[idx];
+                if let Some(crop) =
+                    product_for_species(products, ProductKind::Crop, harvest.species_id)
+                {
+                    stockroom.store_harvest(harvest, crop);
+                }
+            }
+            StockEvent::Treatment(idx) => {
+                let treatment = &treatments[idx];
+                let serving = stockroom.serving_warehouse(herds[treatment.herd_id].pasture_id);
+                stockroom.withdraw(
+                    timestamp,
+                    treatment.total_dose(),
+                    &products[treatment.product_id],
+                    serving,
+                    MovementSource::Treatment(treatment),
+                    config.medicine_delivery_quantity,
+                );
+            }
+            StockEvent::CropSale => {
+                let crops = products.iter().filter(|p| p.kind == ProductKind::Crop);
+                for crop in crops {
+                    stockroom.sell_crop(timestamp, crop);
+                }
+            }
+        }
+    }
+    stockroom.receive(expansion.to_when - Duration::seconds(1));
+}
+
+fn fullest_warehouse<'a>(
+    levels: &StockLevels,
+    warehouses: &'a [Warehouse],
+    product: &Product,
+) -> &'a Warehouse {
+    warehouses
+        .iter()
+        .max_by(|a, b| {
+            levels
+                .quantity(a.id, product.id)
+                .partial_cmp(&levels.quantity(b.id, product.id))
+                .unwrap()
+        })
+        .unwrap()
+}
+
+fn roomiest_warehouse<'a>(levels: &StockLevels, warehouses: &'a [Warehouse]) -> &'a Warehouse {
+    warehouses
+        .iter()
+        .max_by(|a, b| {
+            levels
+                .free_volume(a)
+                .partial_cmp(&levels.free_volume(b))
+                .unwrap()
+        })
+        .unwrap()
+}
@@ -0,0 +1,38 @@
+use rand::prelude::SliceRandom;
+use serde::{Deserialize, Serialize};
+
+use crate::{locale::NamePack, parallel::generator_rng, product::ProductKind};
+
+#[derive(Serialize, Deserialize)]
+pub struct Supplier {
+    pub id: usize,
+    name: String,
+    pub kind: ProductKind, // the kind of products it sells
+}
+
+impl Supplier {
+    pub const fn new(id: usize, name: String, kind: ProductKind) -> Self {
+        Supplier { id, name, kind }
+    }
+}
+
+// Suppliers are spread evenly over the given product kinds
+pub fn expand_supplier_vec(
+    suppliers: &mut Vec<Supplier>,
+    count: usize,
+    kinds: &[ProductKind],
+    name_packs: &[NamePack],
+) {
+    let mut rng = generator_rng();
+
+    for kind in kinds.iter().cycle().take(count) {
+        let name_pack = name_packs.choose(&mut rng).unwrap();
+        let owner = name_pack.surnames.choose(&mut rng).unwrap();
+        let name = match kind {
+            ProductKind::Feed => format!("{} Feeds", owner),
+            ProductKind::Medicine => format!("{} Veterinary Supplies", owner),
+            _ => format!("{} Trading", owner),
+        };
+        suppliers.push(Supplier::new(suppliers.len(), name, *kind));
+    }
+}
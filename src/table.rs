@@ -0,0 +1,115 @@
+use std::ops::{Deref, DerefMut};
+
+// The rows of a fact table, some of which may have been dropped from memory once they were written
+// out, e.g. by a stream; new rows are numbered after all the ones there ever were
+pub struct Table<T> {
+    rows: Vec<T>,
+    dropped_count: usize, // rows no longer in memory still take up their ids
+    written_count: usize, // how many of the rows in memory were written out already
+}
+
+impl<T> Table<T> {
+    pub const fn new() -> Self {
+        Table {
+            rows: vec![],
+            dropped_count: 0,
+            written_count: 0,
+        }
+    }
+
+    // Rows read back from a file, which may skip the ids of the rows that weren't saved
+    pub fn loaded(rows: Vec<T>, id: impl Fn(&T) -> usize) -> Self {
+        let dropped_count = rows.iter().map(|row| id(row) + 1).max().unwrap_or(0) - rows.len();
+        Table {
+            rows,
+            dropped_count,
+            written_count: 0,
+        }
+    }
+
+    pub fn next_id(&self) -> usize {
+        self.dropped_count + self.rows.len()
+    }
+
+    // Pushing to the table rather than its vector lets the row's id be taken in the same call
+    pub fn push(&mut self, row: T) {
+        self.rows.push(row);
+    }
+
+    // The row with the given id, as long as no row after the dropped ones was dropped
+    pub fn by_id(&self, id: usize) -> &T {
+        &self.rows[id - self.dropped_count]
+    }
+
+    // The rows added since the last call, which are taken as written out
+    pub fn take_unwritten(&mut self) -> &[T] {
+        let written_count = self.written_count;
+        self.written_count = self.rows.len();
+        &self.rows[written_count..]
+    }
+
+    // Drops the rows which aren't to be kept, and returns them in their order
+    pub fn drop_unless(&mut self, mut keep: impl FnMut(&T) -> bool) -> Vec<T> {
+        let (mut kept, mut dropped) = (vec![], vec![]);
+        let mut written_count = 0;
+        for (idx, row) in self.rows.drain(..).enumerate() {
+            if keep(&row) {
+                written_count += usize::from(idx < self.written_count);
+                kept.push(row);
+            } else {
+                dropped.push(row);
+            }
+        }
+        self.rows = kept;
+        self.written_count = written_count;
+        self.dropped_count += dropped.len();
+        dropped
+    }
+}
+
+impl<T> From<Vec<T>> for Table<T> {
+    fn from(rows: Vec<T>) -> Self {
+        Table {
+            rows,
+            dropped_count: 0,
+            written_count: 0,
+        }
+    }
+}
+
+// Rows are only ever removed through the table, so that it can count them
+impl<T> Deref for Table<T> {
+    type Target = Vec<T>;
+
+    fn deref(&self) -> &Vec<T> {
+        &self.rows
+    }
+}
+
+impl<T> DerefMut for Table<T> {
+    fn deref_mut(&mut self) -> &mut Vec<T> {
+        &mut self.rows
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ids_continue_after_dropped_rows() {
+        let mut table = Table::from(vec![0, 1, 2, 3]);
+        assert_eq!(table.take_unwritten(), &[0, 1, 2, 3]);
+        assert_eq!(table.drop_unless(|&id| id >= 2), vec![0, 1]);
+        table.push(table.next_id());
+        assert_eq!(table.as_slice(), &[2, 3, 4]);
+        assert_eq!(*table.by_id(3), 3);
+        assert_eq!(table.take_unwritten(), &[4]);
+    }
+
+    #[test]
+    fn loaded_rows_skip_missing_ids() {
+        let table = Table::loaded(vec![0, 2, 5], |&id| id);
+        assert_eq!(table.next_id(), 6);
+    }
+}
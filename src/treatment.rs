@@ -0,0 +1,231 @@
+use std::collections::HashMap;
+
+use chrono::{Duration, NaiveDateTime};
+use rand::{prelude::SliceRandom, Rng};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    health_report::HealthReport,
+    livestock::{DisposalPurpose, Livestock},
+    livestock_movement::{HerdMembership, LivestockMovement, MovementReason},
+    parallel::generator_rng,
+    product::{product_for_species, Product, ProductKind},
+    shift::Roster,
+    snapshot::Expansion,
+    table::Table,
+};
+
+const ILL_COURSE_MIN_DAYS: i64 = 3;
+const ILL_COURSE_MAX_DAYS: i64 = 7;
+const SEVERLY_ILL_COURSE_MIN_DAYS: i64 = 7;
+const SEVERLY_ILL_COURSE_MAX_DAYS: i64 = 14;
+const ILL_DAILY_DOSE: f32 = 1.;
+const SEVERLY_ILL_DAILY_DOSE: f32 = 2.;
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub enum Diagnosis {
+    RespiratoryInfection,
+    DigestiveDisorder,
+    Lameness,
+    Parasites,
+    SkinInfection,
+    Injury,
+}
+
+impl Diagnosis {
+    const ALL: [Diagnosis; 6] = [
+        Diagnosis::RespiratoryInfection,
+        Diagnosis::DigestiveDisorder,
+        Diagnosis::Lameness,
+        Diagnosis::Parasites,
+        Diagnosis::SkinInfection,
+        Diagnosis::Injury,
+    ];
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Treatment {
+    pub id: usize,
+    livestock_id: usize,
+    employee_id: usize,
+    diagnosis: Diagnosis,
+    pub product_id: usize, // the medicine
+    daily_dose: f32,
+    pub start: NaiveDateTime,
+    end: NaiveDateTime,
+
+    // where the animal was at the start, only needed for the expansion the treatment is made in
+    #[serde(skip)]
+    pub herd_id: usize,
+}
+
+impl Treatment {
+    const fn new(id: usize, course: &Course, diagnosis: Diagnosis, medicine: &Product) -> Self {
+        Treatment {
+            id,
+            livestock_id: course.animal.id,
+            employee_id: course.vet_id,
+            diagnosis,
+            product_id: medicine.id,
+            daily_dose: course.daily_dose,
+            start: course.start,
+            end: course.end,
+            herd_id: course.herd_id,
+        }
+    }
+
+    // The whole course is dispensed at its start, a dose for every day begun
+    pub fn total_dose(&self) -> f32 {
+        let days = self.end.signed_duration_since(self.start).num_seconds() as f32 / 86_400.;
+        self.daily_dose * days.ceil().max(1.)
+    }
+}
+
+struct Course<'a> {
+    start: NaiveDateTime,
+    end: NaiveDateTime,
+    animal: &'a Livestock,
+    herd_id: usize,
+    vet_id: usize,
+    daily_dose: f32,
+}
+
+// Animals fall ill at the rate the herd's health reports show, and the vet who examined the herd
+// starts the treatment right away; animals put down for their health are treated until the end,
+// starting when they're isolated if they are
+pub fn expand_treatment_vec(
+    treatments: &mut Table<Treatment>,
+    membership: &HerdMembership,
+    livestock_movements: &[LivestockMovement],
+    health_reports: &[HealthReport],
+    products: &[Product],
+    vets: &Roster,
+    expansion: &Expansion,
+) {
+    let mut rng = generator_rng();
+    let (from_when, to_when) = (expansion.from_when, expansion.to_when);
+    let report_interval = expansion.config.report_interval;
+    let mean_course_days = (ILL_COURSE_MIN_DAYS + SEVERLY_ILL_COURSE_MAX_DAYS) as f64 / 2.;
+    let reports_per_day = 1. / report_interval.num_seconds() as f64 * 86_400.;
+
+    let mut courses = vec![];
+    let isolations: HashMap<usize, NaiveDateTime> = livestock_movements
+        .iter()
+        .filter(|movement| movement.reason == MovementReason::Isolation)
+        .map(|movement| (movement.livestock_id, movement.timestamp))
+        .collect();
+
+    // the last course of the animals disposed for their health, which no other course can overlap
+    let mut last_course_starts = HashMap::new();
+    for animal in membership.livestock() {
+        let disposal = match (animal.disposal, animal.disposal_purpose) {
+            (Some(disposal), Some(DisposalPurpose::Health)) => disposal,
+            _ => continue,
+        };
+        if disposal < from_when || disposal >= to_when {
+            continue;
+        }
+        let (window_start, window_end) = match isolations.get(&animal.id) {
+            Some(&isolation) => (isolation, (isolation + Duration::days(1)).min(disposal)),
+            None => {
+                let course_days =
+                    rng.gen_range(SEVERLY_ILL_COURSE_MIN_DAYS..=SEVERLY_ILL_COURSE_MAX_DAYS);
+                let earliest_start = (disposal - Duration::days(course_days)).max(animal.birth);
+                (earliest_start, disposal)
+            }
+        };
+        // the animal goes untreated while there's no vet employed
+        let (vet, start) = match vets.assign(&mut rng, window_start, window_end) {
+            Some(assignment) => assignment,
+            None => continue,
+        };
+        last_course_starts.insert(animal.id, start);
+        courses.push(Course {
+            start,
+            end: disposal,
+            animal,
+            herd_id: membership.herd_at(animal, start),
+            vet_id: vet.id,
+            daily_dose: SEVERLY_ILL_DAILY_DOSE,
+        });
+    }
+
+    let mut sorted_reports: Vec<&HealthReport> = health_reports.iter().collect();
+    sorted_reports.sort_by_key(|report| report.timestamp);
+
+    let mut sweeps = HashMap::new();
+    let mut treated_until: HashMap<usize, NaiveDateTime> = HashMap::new();
+    for report in sorted_reports {
+        let total_count = report.total_count();
+        if total_count == 0 {
+            continue;
+        }
+        // the reports show how many animals are ill, not how many fall ill in between
+        let falling_ill_pct = |count: u32| {
+            (count as f64 / total_count as f64 / mean_course_days / reports_per_day).min(1.)
+        };
+        let ill_pct = falling_ill_pct(report.ill_count);
+        let severly_ill_pct = falling_ill_pct(report.severly_ill_count);
+
+        let moment = report.timestamp;
+        let members = sweeps
+            .entry(report.herd_id)
+            .or_insert_with(|| membership.sweep(report.herd_id))
+            .members_at(moment);
+        for animal in members {
+            if treated_until
+                .get(&animal.id)
+                .is_some_and(|&end| end > moment)
+                || last_course_starts
+                    .get(&animal.id)
+                    .is_some_and(|&start| start <= moment)
+            {
+                continue;
+            }
+            let (daily_dose, course_days) = if rng.gen_bool(severly_ill_pct) {
+                (
+                    SEVERLY_ILL_DAILY_DOSE,
+                    rng.gen_range(SEVERLY_ILL_COURSE_MIN_DAYS..=SEVERLY_ILL_COURSE_MAX_DAYS),
+                )
+            } else if rng.gen_bool(ill_pct) {
+                (
+                    ILL_DAILY_DOSE,
+                    rng.gen_range(ILL_COURSE_MIN_DAYS..=ILL_COURSE_MAX_DAYS),
+                )
+            } else {
+                continue;
+            };
+            let mut end = moment + Duration::days(course_days);
+            if let Some(&last_start) = last_course_starts.get(&animal.id) {
+                end = end.min(last_start);
+            }
+            if let Some(disposal) = animal.disposal {
+                end = end.min(disposal);
+            }
+            treated_until.insert(animal.id, end);
+            courses.push(Course {
+                start: moment,
+                end,
+                animal,
+                herd_id: report.herd_id,
+                vet_id: report.employee_id,
+                daily_dose,
+            });
+        }
+    }
+
+    courses.sort_by_key(|course| course.start);
+    for course in courses {
+        let medicine =
+            match product_for_species(products, ProductKind::Medicine, course.animal.species_id) {
+                Some(medicine) => medicine,
+                None => continue,
+            };
+        treatments.push(Treatment::new(
+            treatments.next_id(),
+            &course,
+            *Diagnosis::ALL.choose(&mut rng).unwrap(),
+            medicine,
+        ));
+    }
+}
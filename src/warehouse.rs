@@ -1,13 +1,15 @@
-use crate::employee::Employee;
+use crate::employee::{employees_with_role, Employee, EmployeeRole};
 use rand::{prelude::*, Rng};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Serialize)]
+use crate::parallel::generator_rng;
+
+#[derive(Serialize, Deserialize)]
 pub struct Warehouse {
-    id: usize,
+    pub id: usize,
     manager_id: usize,
     area: f32,
-    volume: f32,
+    pub volume: f32,
 }
 
 impl Warehouse {
@@ -21,14 +23,24 @@ impl Warehouse {
     }
 }
 
-pub fn expand_warehouse_vec(
-    warehouses: &mut Vec<Warehouse>,
-    count: usize,
-    employees: &Vec<Employee>,
-) {
-    let mut rng = rand::thread_rng();
+pub fn expand_warehouse_vec(warehouses: &mut Vec<Warehouse>, count: usize, employees: &[Employee]) {
+    let mut rng = generator_rng();
+    // a manager runs only one warehouse
+    let free_managers: Vec<&Employee> =
+        employees_with_role(employees, EmployeeRole::WarehouseManager)
+            .into_iter()
+            .filter(|manager| {
+                !warehouses
+                    .iter()
+                    .any(|warehouse| warehouse.manager_id == manager.id)
+            })
+            .collect();
+    assert!(
+        free_managers.len() >= count,
+        "Not enough warehouse managers for the new warehouses"
+    );
 
-    for manager in employees.choose_multiple(&mut rng, count) {
+    for manager in free_managers.choose_multiple(&mut rng, count) {
         warehouses.push(Warehouse::new(
             warehouses.len(),
             manager,
@@ -43,7 +55,7 @@ pub fn randomly_enlarge_warehouses(
     maximum_extra_area: f32,
     maximum_extra_volume: f32,
 ) {
-    let mut rng = rand::thread_rng();
+    let mut rng = generator_rng();
 
     let to_edit = rng.gen_range(0..warehouses.len());
     for warehouse in warehouses.iter_mut().choose_multiple(&mut rng, to_edit) {
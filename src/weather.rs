@@ -0,0 +1,150 @@
+use std::{convert::TryFrom, f32::consts::PI};
+
+use chrono::{Datelike, NaiveDate};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::parallel::generator_rng;
+
+// Climate of central Poland
+const MEAN_TEMPERATURE: f32 = 8.5; // in degrees Celsius
+const TEMPERATURE_AMPLITUDE: f32 = 10.5;
+const WARMEST_DAY_OF_YEAR: f32 = 200.;
+const TEMPERATURE_PERSISTENCE: f32 = 0.8; // how much of a day's anomaly carries over to the next
+const TEMPERATURE_NOISE: f32 = 2.;
+const WET_AFTER_WET_PCT: f64 = 0.6;
+const WET_AFTER_DRY_PCT: f64 = 0.3;
+const MEAN_PRECIPITATION: f32 = 4.; // in millimeters on a wet day
+const MEAN_HUMIDITY: f32 = 80.; // in percent
+const HUMIDITY_AMPLITUDE: f32 = 8.;
+const WET_DAY_HUMIDITY: f32 = 8.;
+const HUMIDITY_PERSISTENCE: f32 = 0.6;
+const HUMIDITY_NOISE: f32 = 4.;
+const DAYS_IN_YEAR: f32 = 365.;
+
+// Below this the grass hardly grows, so the animals eat more from the feeders
+const GRAZING_MIN_TEMPERATURE: f32 = 15.;
+// Outside of these the animals fall ill more often
+const COMFORT_MIN_TEMPERATURE: f32 = 5.;
+const COMFORT_MAX_TEMPERATURE: f32 = 25.;
+
+#[derive(Serialize, Deserialize)]
+pub struct Weather {
+    pub date: NaiveDate,
+    temperature: f32,   // daily mean, in degrees Celsius
+    precipitation: f32, // in millimeters
+    humidity: f32,      // relative, in percent
+
+    #[serde(skip)]
+    temperature_anomaly: f32,
+    #[serde(skip)]
+    humidity_anomaly: f32,
+}
+
+impl Weather {
+    // Relative to the feed eaten in mild weather
+    pub fn feed_demand_factor(&self) -> f32 {
+        1. + 0.02 * (GRAZING_MIN_TEMPERATURE - self.temperature).max(0.)
+    }
+
+    // Relative to the illness rate in mild, dry weather; cold and damp are the worst, heat also
+    // takes its toll
+    pub fn illness_factor(&self) -> f32 {
+        1. + 0.04 * (COMFORT_MIN_TEMPERATURE - self.temperature).max(0.)
+            + 0.03 * (self.temperature - COMFORT_MAX_TEMPERATURE).max(0.)
+            + 0.02 * self.precipitation.min(20.)
+    }
+}
+
+// Every day gets its seasonal normals plus anomalies which persist for a few days, so cold spells
+// and heat waves last; wet days tend to follow each other
+pub fn expand_weather_vec(weather: &mut Vec<Weather>, first_day: NaiveDate, last_day: NaiveDate) {
+    let mut rng = generator_rng();
+
+    let (mut temperature_anomaly, mut humidity_anomaly, mut was_wet) = match weather.last() {
+        Some(last) => (
+            last.temperature_anomaly,
+            last.humidity_anomaly,
+            last.precipitation > 0.,
+        ),
+        None => (0., 0., false),
+    };
+    let mut day = match weather.last() {
+        Some(last) => last.date.succ().max(first_day),
+        None => first_day,
+    };
+    while day <= last_day {
+        let season = season_of(day);
+        temperature_anomaly = TEMPERATURE_PERSISTENCE * temperature_anomaly
+            + TEMPERATURE_NOISE * standard_normal(&mut rng);
+        humidity_anomaly =
+            HUMIDITY_PERSISTENCE * humidity_anomaly + HUMIDITY_NOISE * standard_normal(&mut rng);
+        let is_wet = rng.gen_bool(if was_wet {
+            WET_AFTER_WET_PCT
+        } else {
+            WET_AFTER_DRY_PCT
+        });
+        let precipitation = if is_wet {
+            // exponentially distributed, with summer showers heavier than winter drizzle
+            let mean = MEAN_PRECIPITATION * (1. + 0.4 * season);
+            -mean * (1. - rng.gen_range(0.0f32..1.0)).ln()
+        } else {
+            0.
+        };
+        let humidity = normal_humidity(season, is_wet) + humidity_anomaly;
+
+        weather.push(Weather {
+            date: day,
+            temperature: round_to_tenth(normal_temperature(season) + temperature_anomaly),
+            precipitation: round_to_tenth(precipitation),
+            humidity: round_to_tenth(humidity.clamp(30., 100.)),
+            temperature_anomaly,
+            humidity_anomaly,
+        });
+        was_wet = is_wet;
+        day = day.succ();
+    }
+}
+
+// The anomalies aren't saved, so they're taken back out of the rounded values, which is close
+// enough for the weather generated after a loaded snapshot to carry on from its last day
+pub fn restore_anomalies(weather: &mut [Weather]) {
+    for day in weather.iter_mut() {
+        let season = season_of(day.date);
+        day.temperature_anomaly = day.temperature - normal_temperature(season);
+        day.humidity_anomaly = day.humidity - normal_humidity(season, day.precipitation > 0.);
+    }
+}
+
+// 1 on the warmest day of the year, -1 half a year later
+fn season_of(day: NaiveDate) -> f32 {
+    (2. * PI * (day.ordinal() as f32 - WARMEST_DAY_OF_YEAR) / DAYS_IN_YEAR).cos()
+}
+
+fn normal_temperature(season: f32) -> f32 {
+    MEAN_TEMPERATURE + TEMPERATURE_AMPLITUDE * season
+}
+
+fn normal_humidity(season: f32, is_wet: bool) -> f32 {
+    MEAN_HUMIDITY - HUMIDITY_AMPLITUDE * season + if is_wet { WET_DAY_HUMIDITY } else { 0. }
+}
+
+// Weather records are consecutive days, so the one for a day is found by its offset
+pub fn weather_on(weather: &[Weather], day: NaiveDate) -> Option<&Weather> {
+    let first = weather.first()?;
+    let offset = day.signed_duration_since(first.date).num_days();
+    usize::try_from(offset)
+        .ok()
+        .and_then(|idx| weather.get(idx))
+}
+
+// Box-Muller transform
+fn standard_normal<R: Rng>(rng: &mut R) -> f32 {
+    let u1: f32 = 1. - rng.gen_range(0.0..1.0);
+    let u2: f32 = rng.gen_range(0.0..1.0);
+    (-2. * u1.ln()).sqrt() * (2. * PI * u2).cos()
+}
+
+fn round_to_tenth(value: f32) -> f32 {
+    (value * 10.).round() / 10.
+}